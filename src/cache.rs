@@ -0,0 +1,231 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+    sync::Mutex,
+};
+
+use crate::project::PyModule;
+
+pub const CACHE_DIR_NAME: &str = ".pydep_cache";
+
+/// The version of what gets stored for a parsed module. Bump it whenever
+/// parsing extracts something new or different, so entries written before
+/// aren't read back with parts missing
+const PARSE_FORMAT_VERSION: u32 = 2;
+
+/// Parsed modules stored on disk, keyed by a hash of everything that went into
+/// parsing them, so unchanged files don't need to be parsed again
+pub struct ParseCache {
+    dir: PathBuf,
+    salt: String,
+    /// Entries read or written since the cache was opened
+    touched: Mutex<HashSet<PathBuf>>,
+}
+
+impl ParseCache {
+    pub fn new(dir: &Path, di_patterns: &[String]) -> io::Result<ParseCache> {
+        fs::create_dir_all(dir)?;
+        let gitignore = dir.join(".gitignore");
+        if !gitignore.exists() {
+            fs::write(gitignore, "*\n")?;
+        }
+        // a new version of pydep may extract modules differently, and so do
+        // different di patterns
+        let salt = format!(
+            "{}\0{}\0{}",
+            env!("CARGO_PKG_VERSION"),
+            PARSE_FORMAT_VERSION,
+            di_patterns.join("\0")
+        );
+        Ok(ParseCache {
+            dir: dir.to_path_buf(),
+            salt,
+            touched: Mutex::new(HashSet::new()),
+        })
+    }
+
+    pub fn get_or_parse(
+        &self,
+        name: &str,
+        path: &Path,
+        source_code: &str,
+        parse: impl FnOnce() -> io::Result<PyModule>,
+    ) -> io::Result<PyModule> {
+        // `proj/a.py` and `./proj/a.py` are the same file, and share an entry
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let mut hasher = blake3::Hasher::new();
+        for part in [&self.salt, name, &canonical.to_string_lossy(), source_code] {
+            hasher.update(part.as_bytes());
+            hasher.update(b"\0");
+        }
+        let entry_path = self
+            .dir
+            .join(format!("{}.json", hasher.finalize().to_hex()));
+        self.touched.lock().unwrap().insert(entry_path.clone());
+
+        if let Some(mut module) = fs::read(&entry_path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<PyModule>(&bytes).ok())
+        {
+            // the entry may have been written with the path spelled another way
+            module.path = path.to_path_buf();
+            return Ok(module);
+        }
+
+        let module = parse()?;
+        // the cache is only an optimization, so failing to write it isn't an error
+        let _ = self.put(&entry_path, &module);
+        Ok(module)
+    }
+
+    /// Delete the entries that weren't read or written since the cache was
+    /// opened, which are for files that changed or are gone, and temporary
+    /// files left by runs that were killed while writing one
+    pub fn prune(&self) -> io::Result<()> {
+        let touched = self.touched.lock().unwrap();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let is_stale = match path.extension().and_then(|extension| extension.to_str()) {
+                Some("json") => !touched.contains(&path),
+                Some("tmp") => true,
+                _ => false,
+            };
+            if is_stale {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    // write to a temporary file first so a concurrent reader never sees half an entry
+    fn put(&self, entry_path: &Path, module: &PyModule) -> io::Result<()> {
+        let tmp_path = entry_path.with_extension(format!("{}.tmp", process::id()));
+        fs::write(&tmp_path, serde_json::to_vec(module)?)?;
+        fs::rename(tmp_path, entry_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::{project::parse_module, testing::TempDir};
+
+    // A cache in a fresh directory, and how many times parsing ran
+    struct TestCache {
+        dir: TempDir,
+        parses: Cell<usize>,
+    }
+
+    impl TestCache {
+        fn new(name: &str) -> TestCache {
+            TestCache {
+                dir: TempDir::new(&format!("cache-{}", name)),
+                parses: Cell::new(0),
+            }
+        }
+
+        fn open(&self, di_patterns: &[String]) -> ParseCache {
+            ParseCache::new(&self.dir.path.join(CACHE_DIR_NAME), di_patterns).unwrap()
+        }
+
+        fn load(&self, cache: &ParseCache, path: &Path, source: &str) -> PyModule {
+            let parse = || {
+                self.parses.set(self.parses.get() + 1);
+                parse_module("pkg.m", source, path, &[])
+            };
+            cache.get_or_parse("pkg.m", path, source, parse).unwrap()
+        }
+
+        fn entries(&self, extension: &str) -> usize {
+            fs::read_dir(self.dir.path.join(CACHE_DIR_NAME))
+                .unwrap()
+                .filter(|entry| {
+                    let path = entry.as_ref().unwrap().path();
+                    path.extension().is_some_and(|found| found == extension)
+                })
+                .count()
+        }
+    }
+
+    #[test]
+    fn unchanged_source_is_a_hit() {
+        let test = TestCache::new("hit");
+        let cache = test.open(&[]);
+        let path = Path::new("pkg/m.py");
+        test.load(&cache, path, "def f():\n    pass\n");
+        let module = test.load(&cache, path, "def f():\n    pass\n");
+        assert_eq!(test.parses.get(), 1);
+        assert_eq!(module.functions[0].name, "f");
+
+        // a new cache over the same directory reads what the first one wrote
+        let cache = test.open(&[]);
+        test.load(&cache, path, "def f():\n    pass\n");
+        assert_eq!(test.parses.get(), 1);
+    }
+
+    #[test]
+    fn changed_source_is_a_miss() {
+        let test = TestCache::new("miss");
+        let cache = test.open(&[]);
+        let path = Path::new("pkg/m.py");
+        test.load(&cache, path, "def f():\n    pass\n");
+        let module = test.load(&cache, path, "def g():\n    pass\n");
+        assert_eq!(test.parses.get(), 2);
+        assert_eq!(module.functions[0].name, "g");
+    }
+
+    #[test]
+    fn other_di_patterns_are_a_miss() {
+        let test = TestCache::new("salt");
+        let path = Path::new("pkg/m.py");
+        let source = "def f():\n    container.get(\"Service\")\n";
+        let cache = test.open(&[]);
+        test.load(&cache, path, source);
+        let cache = test.open(&["container.get".to_string()]);
+        test.load(&cache, path, source);
+        assert_eq!(test.parses.get(), 2);
+    }
+
+    #[test]
+    fn path_spellings_share_an_entry() {
+        let test = TestCache::new("spelling");
+        let cache = test.open(&[]);
+        let path = test.dir.write("m.py", "x = 1\n");
+        let other_spelling = test.dir.path.join(".").join("m.py");
+        test.load(&cache, &path, "x = 1\n");
+        let module = test.load(&cache, &other_spelling, "x = 1\n");
+        assert_eq!(test.parses.get(), 1);
+        assert_eq!(module.path, other_spelling);
+    }
+
+    #[test]
+    fn prune_removes_untouched_entries_and_temporary_files() {
+        let test = TestCache::new("prune");
+        let path = Path::new("pkg/m.py");
+        let cache = test.open(&[]);
+        test.load(&cache, path, "x = 1\n");
+        test.load(&cache, path, "x = 2\n");
+        fs::write(
+            test.dir.path.join(CACHE_DIR_NAME).join("abc.12345.tmp"),
+            "{",
+        )
+        .unwrap();
+        assert_eq!(test.entries("json"), 2);
+
+        let cache = test.open(&[]);
+        test.load(&cache, path, "x = 2\n");
+        cache.prune().unwrap();
+        assert_eq!(test.entries("json"), 1);
+        assert_eq!(test.entries("tmp"), 0);
+        assert!(test
+            .dir
+            .path
+            .join(CACHE_DIR_NAME)
+            .join(".gitignore")
+            .exists());
+    }
+}
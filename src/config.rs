@@ -0,0 +1,204 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::contracts::Contract;
+
+pub const CONFIG_FILE_NAME: &str = "pydep.toml";
+
+/// Project settings, read from pydep.toml or the [tool.pydep] table of pyproject.toml
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Source roots, relative to the directory holding the config file
+    pub roots: Vec<PathBuf>,
+    /// Glob patterns for files and directories to skip, relative to the
+    /// directory holding the config file
+    pub exclude: Vec<String>,
+    /// Functions that look up a class by name, like `container.get("TimService")`
+    pub di_patterns: Vec<String>,
+    pub format: Option<Format>,
+    /// Modules, functions, and classes used from outside the project, like
+    /// `package.cli::main`, counted as live by `pydep dead-code`
+    pub entry_points: Vec<String>,
+    /// File of accepted dead code, relative to the directory holding the
+    /// config file
+    pub dead_code_whitelist: Option<PathBuf>,
+    /// Rules about which modules may import which, checked by `pydep check`
+    pub contracts: Vec<Contract>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+    Ndjson,
+    Dot,
+    Mermaid,
+    Html,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.to_possible_value() {
+            Some(value) => f.write_str(value.get_name()),
+            None => Ok(()),
+        }
+    }
+}
+
+// Only the parts of pyproject.toml we care about. Unknown keys are fine here,
+// it's just [tool.pydep] itself that's strict
+#[derive(Deserialize)]
+struct PyProject {
+    tool: Option<Tool>,
+}
+
+#[derive(Deserialize)]
+struct Tool {
+    pydep: Option<Config>,
+}
+
+// Look for a config file in the project directory and each of its parents,
+// stopping at the first pydep.toml or pyproject.toml with a [tool.pydep] table
+pub fn find_config(project: &Path) -> io::Result<Option<Config>> {
+    let project = project.canonicalize()?;
+    for dir in project.ancestors() {
+        let config_path = dir.join(CONFIG_FILE_NAME);
+        if config_path.is_file() {
+            return load_config(&config_path).map(Some);
+        }
+        let pyproject_path = dir.join("pyproject.toml");
+        if pyproject_path.is_file() {
+            let pyproject: PyProject = read_toml(&pyproject_path)?;
+            if let Some(config) = pyproject.tool.and_then(|tool| tool.pydep) {
+                return Ok(Some(config.relative_to(dir)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+pub fn load_config(path: &Path) -> io::Result<Config> {
+    let config: Config = if path.ends_with("pyproject.toml") {
+        let pyproject: PyProject = read_toml(path)?;
+        pyproject
+            .tool
+            .and_then(|tool| tool.pydep)
+            .unwrap_or_default()
+    } else {
+        read_toml(path)?
+    };
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    Ok(config.relative_to(&dir.canonicalize()?))
+}
+
+impl Config {
+    // Make the paths in the config relative to the absolute directory `dir`.
+    // Exclude patterns become absolute patterns under it
+    fn relative_to(mut self, dir: &Path) -> Config {
+        self.roots = self.roots.iter().map(|root| dir.join(root)).collect();
+        let escaped_dir = globset::escape(&dir.to_string_lossy());
+        self.exclude = self
+            .exclude
+            .iter()
+            .map(|pattern| format!("{}/{}", escaped_dir.trim_end_matches('/'), pattern))
+            .collect();
+        self.dead_code_whitelist = self.dead_code_whitelist.map(|path| dir.join(path));
+        self
+    }
+}
+
+fn read_toml<T: for<'de> Deserialize<'de>>(path: &Path) -> io::Result<T> {
+    let source = fs::read_to_string(path)?;
+    toml::from_str(&source).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), e),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn found_in_a_parent_directory() {
+        let dir = TempDir::new("config-parent");
+        dir.write(CONFIG_FILE_NAME, "format = \"json\"\n");
+        // the nearer pyproject.toml has no [tool.pydep], so the search goes on
+        dir.write("repo/pyproject.toml", "[tool.black]\n");
+        let pyproject = dir.write(
+            "repo/proj/pyproject.toml",
+            "[tool.pydep]\nroots = [\"src\"]\nexclude = [\"gen/**\"]\ndead-code-whitelist = \"dead.txt\"\n",
+        );
+        let project = dir.path.join("repo/proj");
+
+        let config = find_config(&project).unwrap().unwrap();
+        assert_eq!(config.roots, [project.join("src")]);
+        assert_eq!(
+            config.exclude,
+            [format!(
+                "{}/gen/**",
+                globset::escape(&project.to_string_lossy())
+            )]
+        );
+        assert_eq!(config.dead_code_whitelist, Some(project.join("dead.txt")));
+
+        fs::remove_file(pyproject).unwrap();
+        let config = find_config(&project).unwrap().unwrap();
+        assert_eq!(config.format, Some(Format::Json));
+        assert!(config.roots.is_empty());
+    }
+
+    #[test]
+    fn missing_config() {
+        let dir = TempDir::new("config-missing");
+        assert!(find_config(&dir.path).unwrap().is_none());
+    }
+
+    #[test]
+    fn paths_are_relative_to_the_config_file() {
+        let dir = TempDir::new("config-relative");
+        let path = dir.write(
+            "conf/pydep.toml",
+            "roots = [\"../src\"]\nexclude = [\"**/migrations/**\"]\n",
+        );
+
+        let config = load_config(&path).unwrap();
+        let conf = dir.path.join("conf");
+        assert_eq!(config.roots, [conf.join("../src")]);
+        assert_eq!(
+            config.exclude,
+            [format!(
+                "{}/**/migrations/**",
+                globset::escape(&conf.to_string_lossy())
+            )]
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_errors() {
+        let dir = TempDir::new("config-unknown");
+        let path = dir.write(CONFIG_FILE_NAME, "roots = [\"src\"]\nroot = [\"lib\"]\n");
+        let error = load_config(&path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("unknown field `root`"));
+
+        fs::remove_file(&path).unwrap();
+        dir.write("pyproject.toml", "[tool.pydep]\nformats = \"json\"\n");
+        let error = find_config(&dir.path).unwrap_err();
+        assert!(error.to_string().contains("unknown field `formats`"));
+    }
+}
@@ -0,0 +1,412 @@
+use std::{
+    collections::{hash_map::Entry, HashMap, VecDeque},
+    io::{self, Write},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    cycles::SourceLines,
+    graph::{DependencyGraph, EdgeKind, NodeId, NodeKind},
+    project::not_found,
+};
+
+/// A rule about which parts of the project may import which, from the
+/// `contracts` in the config. Modules are named by package, and a package
+/// covers all the modules in it
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum Contract {
+    /// The `source` modules must not import any of the `forbidden` ones,
+    /// which may be outside the project
+    Forbidden {
+        name: Option<String>,
+        source: Vec<String>,
+        forbidden: Vec<String>,
+        /// Also check imports made through other modules
+        #[serde(default)]
+        transitive: bool,
+    },
+    /// None of the `modules` may import another
+    Independent {
+        name: Option<String>,
+        modules: Vec<String>,
+        #[serde(default)]
+        transitive: bool,
+    },
+    /// Each layer may import the layers after it, but not the ones before it
+    Layers {
+        name: Option<String>,
+        layers: Vec<String>,
+        #[serde(default)]
+        transitive: bool,
+    },
+}
+
+impl Contract {
+    /// The contract's name, or a description of it if it has none
+    pub fn describe(&self) -> String {
+        match self {
+            Contract::Forbidden {
+                name: Some(name), ..
+            }
+            | Contract::Independent {
+                name: Some(name), ..
+            }
+            | Contract::Layers {
+                name: Some(name), ..
+            } => name.to_string(),
+            Contract::Forbidden {
+                source, forbidden, ..
+            } => format!(
+                "{} must not import {}",
+                source.join(", "),
+                forbidden.join(", ")
+            ),
+            Contract::Independent { modules, .. } => {
+                format!("{} are independent", modules.join(", "))
+            }
+            Contract::Layers { layers, .. } => format!("layers {}", layers.join(" > ")),
+        }
+    }
+
+    // (importer, imported) pairs of packages that break the contract if
+    // anything in the first imports anything in the second
+    fn rules(&self) -> Vec<(&str, &str)> {
+        let mut rules = vec![];
+        match self {
+            Contract::Forbidden {
+                source, forbidden, ..
+            } => {
+                for source in source {
+                    rules.extend(
+                        forbidden
+                            .iter()
+                            .map(|forbidden| (source.as_str(), forbidden.as_str())),
+                    );
+                }
+            }
+            Contract::Independent { modules, .. } => {
+                for a in modules {
+                    for b in modules {
+                        if a != b {
+                            rules.push((a.as_str(), b.as_str()));
+                        }
+                    }
+                }
+            }
+            Contract::Layers { layers, .. } => {
+                for (index, higher) in layers.iter().enumerate() {
+                    for lower in &layers[index + 1..] {
+                        rules.push((lower.as_str(), higher.as_str()));
+                    }
+                }
+            }
+        }
+        rules
+    }
+
+    // Packages the contract names that have to be in the project
+    fn project_packages(&self) -> &[String] {
+        match self {
+            Contract::Forbidden { source, .. } => source,
+            Contract::Independent { modules, .. } => modules,
+            Contract::Layers { layers, .. } => layers,
+        }
+    }
+
+    fn is_transitive(&self) -> bool {
+        match self {
+            Contract::Forbidden { transitive, .. }
+            | Contract::Independent { transitive, .. }
+            | Contract::Layers { transitive, .. } => *transitive,
+        }
+    }
+}
+
+/// A chain of imports that breaks a contract, as indexes into
+/// [`DependencyGraph::edges`]. Without transitive checking it is a single import
+pub struct Violation {
+    pub contract: usize,
+    pub edges: Vec<usize>,
+}
+
+/// Check each contract against the imports between modules, including
+/// imports inside functions. With `transitive`, every contract also counts
+/// imports made through other modules, as if it set `transitive` itself.
+/// Contracts naming packages that aren't in the project are an error
+pub fn check_contracts(
+    graph: &DependencyGraph,
+    contracts: &[Contract],
+    transitive: bool,
+) -> io::Result<Vec<Violation>> {
+    let modules: Vec<NodeId> = (0..graph.nodes().len())
+        .filter(|&id| graph.node(id).kind == NodeKind::Module)
+        .collect();
+    // imports as (imported module, edge) by importing module
+    let mut imports: HashMap<NodeId, Vec<(NodeId, usize)>> = HashMap::new();
+    for (index, edge) in graph.edges().iter().enumerate() {
+        if edge.kind != EdgeKind::Import {
+            continue;
+        }
+        let Some(from) = graph.find(&graph.node(edge.from).module) else {
+            continue;
+        };
+        if from != edge.to {
+            imports.entry(from).or_default().push((edge.to, index));
+        }
+    }
+
+    let mut violations = vec![];
+    for (number, contract) in contracts.iter().enumerate() {
+        for package in contract.project_packages() {
+            let found = modules.iter().any(|&id| {
+                let node = graph.node(id);
+                !node.external && is_in(&node.fqn, package)
+            });
+            if !found {
+                let names = modules
+                    .iter()
+                    .map(|&id| graph.node(id))
+                    .filter(|node| !node.external)
+                    .map(|node| node.fqn.as_str());
+                let message = format!("contract {}: no module named", contract.describe());
+                return Err(not_found(&message, package, names));
+            }
+        }
+
+        let transitive = transitive || contract.is_transitive();
+        for (importer, imported) in contract.rules() {
+            for &module in &modules {
+                let node = graph.node(module);
+                if node.external || !is_in(&node.fqn, importer) {
+                    continue;
+                }
+                for edges in
+                    forbidden_chains(graph, &imports, module, importer, imported, transitive)
+                {
+                    violations.push(Violation {
+                        contract: number,
+                        edges,
+                    });
+                }
+            }
+        }
+    }
+    Ok(violations)
+}
+
+// The shortest import chain from `module` to each module in `imported` it
+// reaches. Chains stop at the first module in `imported`, and don't pass
+// through other modules in `importer`, which have chains of their own
+fn forbidden_chains(
+    graph: &DependencyGraph,
+    imports: &HashMap<NodeId, Vec<(NodeId, usize)>>,
+    module: NodeId,
+    importer: &str,
+    imported: &str,
+    transitive: bool,
+) -> Vec<Vec<usize>> {
+    let mut chains = vec![];
+    let mut reached_by: HashMap<NodeId, (NodeId, usize)> = HashMap::new();
+    let mut queue = VecDeque::from([module]);
+    while let Some(id) = queue.pop_front() {
+        for &(to, edge) in imports.get(&id).into_iter().flatten() {
+            let Entry::Vacant(entry) = reached_by.entry(to) else {
+                continue;
+            };
+            if to == module {
+                continue;
+            }
+            entry.insert((id, edge));
+            let name = &graph.node(to).fqn;
+            if is_in(name, imported) {
+                let mut edges = vec![];
+                let mut current = to;
+                while current != module {
+                    let (previous, edge) = reached_by[&current];
+                    edges.push(edge);
+                    current = previous;
+                }
+                edges.reverse();
+                chains.push(edges);
+            } else if transitive && !is_in(name, importer) {
+                queue.push_back(to);
+            }
+        }
+    }
+    chains
+}
+
+// Whether a module is the package or inside it
+fn is_in(module: &str, package: &str) -> bool {
+    module == package
+        || module
+            .strip_prefix(package)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
+
+/// Write each broken contract with the import chains that break it, and how
+/// many contracts were kept
+pub fn write_violations(
+    out: &mut impl Write,
+    graph: &DependencyGraph,
+    contracts: &[Contract],
+    violations: &[Violation],
+) -> io::Result<()> {
+    let mut sources = SourceLines::default();
+    let mut broken = 0;
+    for (number, contract) in contracts.iter().enumerate() {
+        let mut chains = violations
+            .iter()
+            .filter(|violation| violation.contract == number)
+            .peekable();
+        if chains.peek().is_none() {
+            continue;
+        }
+        if broken > 0 {
+            writeln!(out)?;
+        }
+        broken += 1;
+        writeln!(out, "broken: {}", contract.describe())?;
+        for violation in chains {
+            let first = &graph.edges()[violation.edges[0]];
+            let mut path = vec![graph.node(first.from).module.as_str()];
+            path.extend(
+                violation
+                    .edges
+                    .iter()
+                    .map(|&edge| graph.node(graph.edges()[edge].to).fqn.as_str()),
+            );
+            writeln!(out, "  {}", path.join(" -> "))?;
+            for &index in &violation.edges {
+                sources.write_import(out, graph, index, "    ")?;
+            }
+        }
+    }
+    if broken > 0 {
+        writeln!(out)?;
+    }
+    writeln!(
+        out,
+        "contracts: {} kept, {} broken",
+        contracts.len() - broken,
+        broken
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::build_graph;
+
+    fn graph() -> DependencyGraph {
+        build_graph(&[
+            ("ui/views.py", "import services.api\nimport db2.cache\n"),
+            ("services/api.py", "import db.models\n"),
+            ("db/models.py", ""),
+            ("db/seed.py", "import ui.views\n"),
+            ("db2/cache.py", ""),
+        ])
+    }
+
+    // Each violation as the modules along its import chain
+    fn check(graph: &DependencyGraph, contract: Contract) -> Vec<Vec<String>> {
+        let violations = check_contracts(graph, &[contract], false).unwrap();
+        violations
+            .iter()
+            .map(|violation| {
+                let first = &graph.edges()[violation.edges[0]];
+                let mut chain = vec![graph.node(first.from).module.to_string()];
+                chain.extend(
+                    violation
+                        .edges
+                        .iter()
+                        .map(|&edge| graph.node(graph.edges()[edge].to).fqn.to_string()),
+                );
+                chain
+            })
+            .collect()
+    }
+
+    fn forbidden(source: &str, forbidden: &str, transitive: bool) -> Contract {
+        Contract::Forbidden {
+            name: None,
+            source: vec![source.to_string()],
+            forbidden: vec![forbidden.to_string()],
+            transitive,
+        }
+    }
+
+    #[test]
+    fn forbidden_imports() {
+        let graph = graph();
+        assert_eq!(
+            check(&graph, forbidden("ui", "services", false)),
+            [["ui.views", "services.api"]]
+        );
+        // ui only reaches db through services
+        assert!(check(&graph, forbidden("ui", "db", false)).is_empty());
+        assert_eq!(
+            check(&graph, forbidden("ui", "db", true)),
+            [["ui.views", "services.api", "db.models"]]
+        );
+    }
+
+    #[test]
+    fn independent_modules() {
+        let graph = graph();
+        let contract = Contract::Independent {
+            name: None,
+            modules: vec!["ui".to_string(), "services".to_string(), "db2".to_string()],
+            transitive: false,
+        };
+        assert_eq!(
+            check(&graph, contract),
+            [["ui.views", "services.api"], ["ui.views", "db2.cache"]]
+        );
+    }
+
+    #[test]
+    fn lower_layers_must_not_import_higher_ones() {
+        let graph = graph();
+        let contract = Contract::Layers {
+            name: None,
+            layers: vec!["ui".to_string(), "services".to_string(), "db".to_string()],
+            transitive: false,
+        };
+        assert_eq!(check(&graph, contract), [["db.seed", "ui.views"]]);
+    }
+
+    #[test]
+    fn packages_do_not_cover_packages_with_longer_names() {
+        assert!(is_in("pkg", "pkg"));
+        assert!(is_in("pkg.sub.m", "pkg"));
+        assert!(!is_in("pkg2", "pkg"));
+        assert!(!is_in("pkg2.m", "pkg"));
+        assert!(!is_in("pkg", "pkg.sub"));
+
+        // ui imports db2, which isn't part of db
+        let graph = graph();
+        assert_eq!(
+            check(&graph, forbidden("ui", "db", true)),
+            [["ui.views", "services.api", "db.models"]]
+        );
+        assert_eq!(
+            check(&graph, forbidden("ui", "db2", false)),
+            [["ui.views", "db2.cache"]]
+        );
+    }
+
+    #[test]
+    fn unknown_packages_are_an_error() {
+        let error = check_contracts(&graph(), &[forbidden("ui.view", "db", false)], false)
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "contract ui.view must not import db: no module named ui.view, did you mean ui.views?"
+        );
+    }
+}
@@ -0,0 +1,355 @@
+use std::{
+    collections::{hash_map::Entry, BTreeSet, HashMap, VecDeque},
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::graph::{strongly_connected_components, DependencyGraph, EdgeKind, NodeId, NodeKind};
+
+/// A group of modules that import each other, directly or through each other
+pub struct ImportCycle {
+    /// Every module in the group, sorted by name
+    pub modules: Vec<String>,
+    /// One concrete cycle through some of the modules, as indexes into
+    /// [`DependencyGraph::edges`]. The last import leads back to the module
+    /// the first one is in
+    pub edges: Vec<usize>,
+    /// Whether the cycle needs an import made inside a function or under
+    /// `if TYPE_CHECKING:`. Those only run when the function is called, or
+    /// never, so they don't fail at import time
+    pub deferred: bool,
+}
+
+/// Find the groups of project modules that import each other. Each comes
+/// with its shortest cycle that only uses module level imports that run, or
+/// if there is none, its shortest cycle using deferred ones too
+pub fn find_import_cycles(graph: &DependencyGraph) -> Vec<ImportCycle> {
+    let modules: Vec<NodeId> = (0..graph.nodes().len())
+        .filter(|&id| {
+            let node = graph.node(id);
+            node.kind == NodeKind::Module && !node.external
+        })
+        .collect();
+    let index: HashMap<NodeId, usize> = modules
+        .iter()
+        .enumerate()
+        .map(|(index, &id)| (id, index))
+        .collect();
+
+    // imports between project modules, as (imported module, edge) by module
+    let mut imports: Vec<Vec<(usize, usize)>> = vec![vec![]; modules.len()];
+    for (edge_index, edge) in graph.edges().iter().enumerate() {
+        if edge.kind != EdgeKind::Import {
+            continue;
+        }
+        let from = graph.find(&graph.node(edge.from).module).unwrap();
+        if let (Some(&from), Some(&to)) = (index.get(&from), index.get(&edge.to)) {
+            imports[from].push((to, edge_index));
+        }
+    }
+    let successors: Vec<Vec<usize>> = imports
+        .iter()
+        .map(|imports| imports.iter().map(|&(to, _)| to).collect())
+        .collect();
+
+    let mut cycles = vec![];
+    for component in strongly_connected_components(&successors) {
+        let is_cycle = component.len() > 1 || successors[component[0]].contains(&component[0]);
+        if !is_cycle {
+            continue;
+        }
+        let members: BTreeSet<usize> = component.iter().copied().collect();
+        let mut names: Vec<&str> = component
+            .iter()
+            .map(|&member| graph.node(modules[member]).fqn.as_str())
+            .collect();
+        names.sort_unstable();
+
+        let is_deferred = |edge: usize| {
+            let edge = &graph.edges()[edge];
+            edge.type_checking || graph.node(edge.from).kind != NodeKind::Module
+        };
+        let (edges, deferred) = match shortest_cycle(&members, &imports, |edge| !is_deferred(edge))
+        {
+            Some(edges) => (edges, false),
+            None => (shortest_cycle(&members, &imports, |_| true).unwrap(), true),
+        };
+        cycles.push(ImportCycle {
+            modules: names.iter().map(|name| name.to_string()).collect(),
+            edges,
+            deferred,
+        });
+    }
+    cycles.sort_by(|a, b| a.modules.cmp(&b.modules));
+    cycles
+}
+
+// The shortest cycle inside a group of modules, using only allowed edges.
+// Ties go to the cycle through the module that comes first
+fn shortest_cycle(
+    members: &BTreeSet<usize>,
+    imports: &[Vec<(usize, usize)>],
+    allowed: impl Fn(usize) -> bool,
+) -> Option<Vec<usize>> {
+    let mut best: Option<Vec<usize>> = None;
+    for &start in members {
+        // breadth first from start, remembering the edge each module was reached by
+        let mut reached_by: HashMap<usize, (usize, usize)> = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+        let mut closing = None;
+        'search: while let Some(module) = queue.pop_front() {
+            for &(to, edge) in &imports[module] {
+                if !members.contains(&to) || !allowed(edge) {
+                    continue;
+                }
+                if to == start {
+                    closing = Some((module, edge));
+                    break 'search;
+                }
+                if let Entry::Vacant(entry) = reached_by.entry(to) {
+                    entry.insert((module, edge));
+                    queue.push_back(to);
+                }
+            }
+        }
+        let Some((mut module, edge)) = closing else {
+            continue;
+        };
+        let mut edges = vec![edge];
+        while module != start {
+            let (previous, edge) = reached_by[&module];
+            edges.push(edge);
+            module = previous;
+        }
+        edges.reverse();
+        if best.as_ref().is_none_or(|best| edges.len() < best.len()) {
+            best = Some(edges);
+        }
+    }
+    best
+}
+
+/// Modules that were already known to import each other, one group per line
+/// with the module names separated by spaces. Lines starting with `#` are
+/// comments
+pub struct CycleBaseline {
+    groups: Vec<BTreeSet<String>>,
+}
+
+impl CycleBaseline {
+    pub fn load(path: &Path) -> io::Result<CycleBaseline> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+        let groups = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| line.split_whitespace().map(str::to_string).collect())
+            .collect();
+        Ok(CycleBaseline { groups })
+    }
+
+    pub fn save(path: &Path, cycles: &[ImportCycle]) -> io::Result<()> {
+        let mut text = String::from("# import cycles known to pydep cycles\n");
+        for cycle in cycles {
+            text.push_str(&cycle.modules.join(" "));
+            text.push('\n');
+        }
+        fs::write(path, text)
+    }
+
+    /// Whether the cycle is new, rather than one of the known groups or part
+    /// of one
+    pub fn is_new(&self, cycle: &ImportCycle) -> bool {
+        !self
+            .groups
+            .iter()
+            .any(|group| cycle.modules.iter().all(|module| group.contains(module)))
+    }
+}
+
+/// Write each cycle with the import statements that make it up. Cycles the
+/// baseline doesn't know about are marked as new
+pub fn write_cycles(
+    out: &mut impl Write,
+    graph: &DependencyGraph,
+    cycles: &[ImportCycle],
+    baseline: Option<&CycleBaseline>,
+) -> io::Result<()> {
+    let mut sources = SourceLines::default();
+    for (number, cycle) in cycles.iter().enumerate() {
+        if number > 0 {
+            writeln!(out)?;
+        }
+        let is_new = baseline.is_some_and(|baseline| baseline.is_new(cycle));
+        let mut path: Vec<&str> = cycle
+            .edges
+            .iter()
+            .map(|&edge| graph.node(graph.edges()[edge].from).module.as_str())
+            .collect();
+        path.push(path[0]);
+        writeln!(
+            out,
+            "{}cycle {}{}: {}",
+            if cycle.deferred { "deferred " } else { "" },
+            number + 1,
+            if is_new { " (new)" } else { "" },
+            path.join(" -> ")
+        )?;
+        if cycle.modules.len() > path.len() - 1 {
+            writeln!(
+                out,
+                "  in a group of {} modules: {}",
+                cycle.modules.len(),
+                cycle.modules.join(", ")
+            )?;
+        }
+        for &index in &cycle.edges {
+            sources.write_import(out, graph, index, "  ")?;
+        }
+    }
+    Ok(())
+}
+
+// Lines of source files, read the first time they're asked for
+#[derive(Default)]
+pub(crate) struct SourceLines {
+    files: HashMap<PathBuf, Option<Vec<String>>>,
+}
+
+impl SourceLines {
+    pub(crate) fn line(&mut self, path: &Path, line: usize) -> Option<String> {
+        let lines = self.files.entry(path.to_path_buf()).or_insert_with(|| {
+            fs::read_to_string(path)
+                .ok()
+                .map(|text| text.lines().map(str::to_string).collect())
+        });
+        lines.as_ref()?.get(line.checked_sub(1)?).cloned()
+    }
+
+    /// Write an import edge as its location and line of source, noting
+    /// imports made inside a function or only for type checking
+    pub(crate) fn write_import(
+        &mut self,
+        out: &mut impl Write,
+        graph: &DependencyGraph,
+        index: usize,
+        indent: &str,
+    ) -> io::Result<()> {
+        let edge = &graph.edges()[index];
+        let from = graph.node(edge.from);
+        let location = match &from.path {
+            Some(path) => format!("{}:{}", path.display(), edge.location.line),
+            None => from.module.to_string(),
+        };
+        let source = from
+            .path
+            .as_ref()
+            .and_then(|path| self.line(path, edge.location.line))
+            .unwrap_or_default();
+        write!(out, "{}{}  {}", indent, location, source.trim())?;
+        if from.kind != NodeKind::Module {
+            write!(out, "  (deferred, in {})", from.fqn)?;
+        }
+        if edge.type_checking {
+            write!(out, "  (type checking only)")?;
+        }
+        writeln!(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{build_graph, TempDir};
+
+    fn baseline(name: &str, text: &str) -> CycleBaseline {
+        let dir = TempDir::new(name);
+        CycleBaseline::load(&dir.write("baseline.txt", text)).unwrap()
+    }
+
+    #[test]
+    fn cycles_are_found_with_their_modules() {
+        let graph = build_graph(&[
+            ("a.py", "import b\n"),
+            ("b.py", "import c\n"),
+            ("c.py", "import a\n"),
+            ("d.py", "import a\n"),
+        ]);
+        let cycles = find_import_cycles(&graph);
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].modules, ["a", "b", "c"]);
+        assert_eq!(cycles[0].edges.len(), 3);
+        assert!(!cycles[0].deferred);
+    }
+
+    #[test]
+    fn cycles_missing_from_the_baseline_are_new() {
+        let graph = build_graph(&[
+            ("a.py", "import b\n"),
+            ("b.py", "import a\n"),
+            ("c.py", "import d\n"),
+            ("d.py", "import c\n"),
+        ]);
+        let cycles = find_import_cycles(&graph);
+        let baseline = baseline("cycles-new", "# known\na b\n");
+        assert_eq!(cycles[0].modules, ["a", "b"]);
+        assert!(!baseline.is_new(&cycles[0]));
+        assert_eq!(cycles[1].modules, ["c", "d"]);
+        assert!(baseline.is_new(&cycles[1]));
+    }
+
+    #[test]
+    fn cycles_inside_a_baselined_group_are_known() {
+        let graph = build_graph(&[("a.py", "import b\n"), ("b.py", "import a\n")]);
+        let cycles = find_import_cycles(&graph);
+        assert!(!baseline("cycles-subset", "a b c\n").is_new(&cycles[0]));
+        // a group that grew is new, even if part of it was known
+        assert!(baseline("cycles-grown", "a\n").is_new(&cycles[0]));
+    }
+
+    #[test]
+    fn baseline_round_trips() {
+        let graph = build_graph(&[("a.py", "import b\n"), ("b.py", "import a\n")]);
+        let cycles = find_import_cycles(&graph);
+        let dir = TempDir::new("cycles-save");
+        let path = dir.path.join("baseline.txt");
+        CycleBaseline::save(&path, &cycles).unwrap();
+        assert!(!CycleBaseline::load(&path).unwrap().is_new(&cycles[0]));
+    }
+
+    #[test]
+    fn deferred_imports_make_deferred_cycles() {
+        let function_level =
+            build_graph(&[("a.py", "import b\n"), ("b.py", "def f():\n    import a\n")]);
+        let type_checking = build_graph(&[
+            ("a.py", "import b\n"),
+            (
+                "b.py",
+                "from typing import TYPE_CHECKING\nif TYPE_CHECKING:\n    import a\n",
+            ),
+        ]);
+        for graph in [function_level, type_checking] {
+            let cycles = find_import_cycles(&graph);
+            assert_eq!(cycles.len(), 1);
+            assert!(cycles[0].deferred);
+        }
+    }
+
+    #[test]
+    fn cycles_prefer_imports_that_run() {
+        // b imports a both ways, and the cycle is shown through the one at
+        // module level, even though the deferred one comes first
+        let graph = build_graph(&[
+            ("a.py", "import b\n"),
+            ("b.py", "def f():\n    import a\n\nimport a\n"),
+        ]);
+        let cycles = find_import_cycles(&graph);
+        assert!(!cycles[0].deferred);
+        let edges = &cycles[0].edges;
+        assert!(edges
+            .iter()
+            .all(|&edge| graph.node(graph.edges()[edge].from).kind == NodeKind::Module));
+    }
+}
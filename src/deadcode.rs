@@ -0,0 +1,605 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use toml::Value;
+
+use crate::{
+    graph::{
+        resolve_relative_import, DependencyGraph, EdgeKind, NodeId, NodeKind, UnresolvedReason,
+    },
+    project::{not_found, Project, PyFunction, PyModule},
+    table::Table,
+};
+
+/// Decorators that register a function with a framework, which then calls
+/// it, like `@app.route("/")`, `@router.get("/")` or `@pytest.fixture`.
+/// Only the last part of the decorator's name is compared
+const FRAMEWORK_DECORATORS: &str = "
+    route get post put patch delete head options websocket api_view
+    task shared_task periodic_task command group callback fixture
+    receiver on_event listens_for hookimpl
+";
+
+/// How sure pydep is that unused code really is unused
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum)]
+pub enum Confidence {
+    /// It may be used in ways pydep can't follow, like a method called on a
+    /// variable or a function a decorator registers somewhere
+    Low,
+    /// Only code that is unused itself refers to it
+    Medium,
+    /// Nothing refers to it at all
+    High,
+}
+
+impl Confidence {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Confidence::Low => "low",
+            Confidence::Medium => "medium",
+            Confidence::High => "high",
+        }
+    }
+}
+
+/// A function, method, or class that nothing reachable from an entry point uses
+#[derive(Debug, Clone)]
+pub struct DeadCode {
+    pub node: NodeId,
+    pub confidence: Confidence,
+    /// Why it has the confidence it has
+    pub reason: &'static str,
+}
+
+/// Where running the project can start: modules with a `__main__` block,
+/// console scripts from pyproject.toml, test files, functions registered
+/// with a framework by a decorator, and the configured `entry_points`.
+/// Configured names that aren't in the project are an error
+pub fn find_entry_points(project: &Project, configured: &[String]) -> io::Result<Vec<NodeId>> {
+    let graph = &project.graph;
+    let mut entry_points = vec![];
+    for name in configured {
+        let Some(id) = graph.find(name) else {
+            let names = graph.nodes().iter().map(|node| node.fqn.as_str());
+            return Err(not_found("unknown entry point", name, names));
+        };
+        entry_points.push(id);
+    }
+
+    // scripts may name things pydep didn't find, like code in a dependency
+    for script in find_console_scripts(&project.path)? {
+        let fqn = match script.split_once(':') {
+            Some((module, object)) => format!("{}::{}", module.trim(), object.trim()),
+            None => script.trim().to_string(),
+        };
+        entry_points.extend(graph.find(&fqn));
+    }
+
+    for module in project.modules.values() {
+        let find = |fqn: String| graph.find(&fqn);
+        let is_test = is_test_module(module);
+        if is_test || module.main_block {
+            entry_points.extend(find(module.name.to_string()));
+        }
+        let is_entry = |function: &PyFunction| is_test || is_registered(function);
+        for function in &module.functions {
+            if is_entry(function) {
+                entry_points.extend(find(format!("{}::{}", module.name, function.name)));
+            }
+        }
+        for class in &module.classes {
+            if is_test {
+                entry_points.extend(find(format!("{}::{}", module.name, class.name)));
+            }
+            for method in &class.methods {
+                if is_entry(method) {
+                    entry_points.extend(find(format!(
+                        "{}::{}.{}",
+                        module.name, class.name, method.name
+                    )));
+                }
+            }
+        }
+    }
+    Ok(entry_points)
+}
+
+/// Test directories at the top of the project that no source root covers, as
+/// in a src layout. Their tests aren't parsed, so code only they use looks
+/// unused
+pub fn find_unparsed_test_dirs(project: &Project) -> Vec<PathBuf> {
+    ["tests", "test"]
+        .into_iter()
+        .map(|name| project.path.join(name))
+        .filter(|dir| dir.is_dir() && !project.roots.iter().any(|root| dir.starts_with(&root.path)))
+        .collect()
+}
+
+// The `module:function` references of [project.scripts], [project.gui-scripts]
+// and [tool.poetry.scripts] in pyproject.toml
+fn find_console_scripts(project: &Path) -> io::Result<Vec<String>> {
+    let pyproject_path = project.join("pyproject.toml");
+    if !pyproject_path.is_file() {
+        return Ok(vec![]);
+    }
+    let source = fs::read_to_string(&pyproject_path)?;
+    let pyproject: Value = toml::from_str(&source).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", pyproject_path.display(), e),
+        )
+    })?;
+
+    let tables = [
+        pyproject.get("project").and_then(|p| p.get("scripts")),
+        pyproject.get("project").and_then(|p| p.get("gui-scripts")),
+        pyproject
+            .get("tool")
+            .and_then(|tool| tool.get("poetry"))
+            .and_then(|poetry| poetry.get("scripts")),
+    ];
+    let mut scripts = vec![];
+    for table in tables.into_iter().flatten() {
+        let Some(table) = table.as_table() else {
+            continue;
+        };
+        for value in table.values() {
+            // poetry also allows { reference = "module:function", type = "console" }
+            let reference = value
+                .as_str()
+                .or_else(|| value.get("reference").and_then(Value::as_str));
+            scripts.extend(reference.map(str::to_string));
+        }
+    }
+    Ok(scripts)
+}
+
+// Files pytest collects: test_*.py, *_test.py, conftest.py, and anything in a
+// tests directory
+fn is_test_module(module: &PyModule) -> bool {
+    let stem = module
+        .path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    stem.starts_with("test_")
+        || stem.ends_with("_test")
+        || stem == "conftest"
+        || module
+            .name
+            .split('.')
+            .any(|part| part == "tests" || part == "test")
+}
+
+// Top level functions and classes whose name is read somewhere without
+// calling it, like `Thread(target=worker)`, `map(f, xs)` or a dict of
+// handlers, in the module that defines them or one that imports them by name
+fn find_referenced(project: &Project) -> HashSet<String> {
+    let mut referenced = HashSet::new();
+    for module in project.modules.values() {
+        let defined = module
+            .functions
+            .iter()
+            .map(|function| &function.name)
+            .chain(module.classes.iter().map(|class| &class.name));
+        for name in defined {
+            if module.names.contains(name) {
+                referenced.insert(format!("{}::{}", module.name, name));
+            }
+        }
+        for import_from in &module.import_froms {
+            let Some(base) =
+                resolve_relative_import(module, &import_from.module, import_from.level)
+            else {
+                continue;
+            };
+            for name in &import_from.names {
+                if module.names.contains(name.bound_name()) {
+                    referenced.insert(format!("{}::{}", base, name.name));
+                }
+            }
+        }
+    }
+    referenced
+}
+
+fn is_registered(function: &PyFunction) -> bool {
+    function.decorators.iter().any(|decorator| {
+        let last = decorator.name.rsplit('.').next().unwrap_or_default();
+        FRAMEWORK_DECORATORS
+            .split_whitespace()
+            .any(|name| name == last)
+    })
+}
+
+/// Find the project's functions, methods, and classes that nothing reachable
+/// from the entry points uses, sorted by where they are. Methods of unused
+/// classes aren't listed separately
+pub fn find_dead_code(project: &Project, entry_points: &[NodeId]) -> Vec<DeadCode> {
+    let graph = &project.graph;
+    let live = find_live(graph, entry_points);
+
+    // names called on values pydep can't follow, any of which could be a method
+    let dynamic_names: HashSet<&str> = graph
+        .unresolved_calls()
+        .iter()
+        .filter(|call| call.reason == UnresolvedReason::DynamicAttribute)
+        .filter_map(|call| call.name.rsplit('.').next())
+        .collect();
+    let decorated: HashSet<String> = project
+        .modules
+        .values()
+        .flat_map(|module| {
+            let functions = module
+                .functions
+                .iter()
+                .map(move |function| (format!("{}::{}", module.name, function.name), function));
+            let methods = module.classes.iter().flat_map(move |class| {
+                class.methods.iter().map(move |method| {
+                    (
+                        format!("{}::{}.{}", module.name, class.name, method.name),
+                        method,
+                    )
+                })
+            });
+            functions.chain(methods)
+        })
+        .filter(|(_, function)| !function.decorators.is_empty())
+        .map(|(fqn, _)| fqn)
+        .collect();
+    let referenced = find_referenced(project);
+
+    let mut dead = vec![];
+    for (id, node) in graph.nodes().iter().enumerate() {
+        let defines_code = matches!(
+            node.kind,
+            NodeKind::Class | NodeKind::Function | NodeKind::Method
+        );
+        if !defines_code || node.external || live.contains(&id) {
+            continue;
+        }
+        let class = (node.kind == NodeKind::Method)
+            .then(|| class_of(graph, id))
+            .flatten();
+        if class.is_some_and(|class| !live.contains(&class)) {
+            continue;
+        }
+
+        let name = node.fqn.rsplit(['.', ':']).next().unwrap_or_default();
+        let (confidence, reason) = if node.kind == NodeKind::Method && dynamic_names.contains(name)
+        {
+            (
+                Confidence::Low,
+                "a method of this name is called dynamically",
+            )
+        } else if class.is_some_and(|class| overrides(graph, class, name)) {
+            (Confidence::Low, "overrides a base class method")
+        } else if decorated.contains(&node.fqn) {
+            (Confidence::Low, "decorated")
+        } else if is_dunder(name) {
+            (Confidence::Low, "special name python may use")
+        } else if graph.dependents(id).next().is_some() {
+            (Confidence::Medium, "only used by unused code")
+        } else if referenced.contains(&node.fqn) {
+            (Confidence::Low, "referenced without being called")
+        } else {
+            (Confidence::High, "never used")
+        };
+        dead.push(DeadCode {
+            node: id,
+            confidence,
+            reason,
+        });
+    }
+    dead.sort_by(|a, b| {
+        let (a, b) = (graph.node(a.node), graph.node(b.node));
+        (&a.path, a.location).cmp(&(&b.path, b.location))
+    });
+    dead
+}
+
+// Everything reachable from the entry points through any kind of dependency.
+// Importing a module runs its packages' __init__.py first, and python calls
+// the special methods of classes that are used
+fn find_live(graph: &DependencyGraph, entry_points: &[NodeId]) -> HashSet<NodeId> {
+    let mut live: HashSet<NodeId> = HashSet::new();
+    let mut queue: VecDeque<NodeId> = VecDeque::new();
+    let mut reach = |id: NodeId, queue: &mut VecDeque<NodeId>| {
+        if live.insert(id) {
+            queue.push_back(id);
+        }
+    };
+    for &id in entry_points {
+        reach(id, &mut queue);
+    }
+
+    // special methods by the class they belong to
+    let mut special_methods: Vec<Vec<NodeId>> = vec![vec![]; graph.nodes().len()];
+    for (id, node) in graph.nodes().iter().enumerate() {
+        let name = node.fqn.rsplit('.').next().unwrap_or_default();
+        if node.kind == NodeKind::Method && is_dunder(name) {
+            if let Some(class) = class_of(graph, id) {
+                special_methods[class].push(id);
+            }
+        }
+    }
+
+    while let Some(id) = queue.pop_front() {
+        for edge in graph.dependencies(id) {
+            reach(edge.to, &mut queue);
+        }
+        let node = graph.node(id);
+        match node.kind {
+            NodeKind::Module => {
+                for (index, _) in node.fqn.match_indices('.') {
+                    if let Some(package) = graph.find(&node.fqn[..index]) {
+                        reach(package, &mut queue);
+                    }
+                }
+            }
+            NodeKind::Class => {
+                for &method in &special_methods[id] {
+                    reach(method, &mut queue);
+                }
+            }
+            _ => {}
+        }
+    }
+    live
+}
+
+fn class_of(graph: &DependencyGraph, method: NodeId) -> Option<NodeId> {
+    let (class, _) = graph.node(method).fqn.rsplit_once('.')?;
+    graph.find(class)
+}
+
+// Whether a base class of `class`, or one of theirs, has a method named `name`
+fn overrides(graph: &DependencyGraph, class: NodeId, name: &str) -> bool {
+    let mut seen = HashSet::from([class]);
+    let mut queue = VecDeque::from([class]);
+    while let Some(id) = queue.pop_front() {
+        for edge in graph.dependencies(id) {
+            if edge.kind != EdgeKind::Inherit || !seen.insert(edge.to) {
+                continue;
+            }
+            let base = graph.node(edge.to);
+            // a method of an external base class may well be overridden, but
+            // pydep can't tell
+            if base.external || graph.find(&format!("{}.{}", base.fqn, name)).is_some() {
+                return true;
+            }
+            queue.push_back(edge.to);
+        }
+    }
+    false
+}
+
+fn is_dunder(name: &str) -> bool {
+    name.len() > 4 && name.starts_with("__") && name.ends_with("__")
+}
+
+/// Unused code that has been accepted, as glob patterns over fully qualified
+/// names like `package.module::Class.*`, one per line. Lines starting with
+/// `#` are comments
+pub struct Whitelist {
+    patterns: GlobSet,
+}
+
+impl Whitelist {
+    pub fn load(path: &Path) -> io::Result<Whitelist> {
+        let error = |e: &dyn std::fmt::Display| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        };
+        let text = fs::read_to_string(path)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+        let mut builder = GlobSetBuilder::new();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            builder.add(Glob::new(line).map_err(|e| error(&e))?);
+        }
+        Ok(Whitelist {
+            patterns: builder.build().map_err(|e| error(&e))?,
+        })
+    }
+
+    pub fn contains(&self, fqn: &str) -> bool {
+        self.patterns.is_match(fqn)
+    }
+}
+
+/// Write each finding with where it is, and how many there are of each
+/// confidence
+pub fn write_dead_code(
+    out: &mut impl Write,
+    graph: &DependencyGraph,
+    dead: &[DeadCode],
+) -> io::Result<()> {
+    let mut table = Table::new(&["location", "name", "kind", "confidence", "reason"]);
+    for finding in dead {
+        let node = graph.node(finding.node);
+        let path = node
+            .path
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_default();
+        let line = node.location.map_or(0, |location| location.line);
+        table.push(vec![
+            format!("{}:{}", path, line),
+            node.fqn.to_string(),
+            node.kind.as_str().to_string(),
+            finding.confidence.as_str().to_string(),
+            finding.reason.to_string(),
+        ]);
+    }
+    table.write_text(out)?;
+
+    writeln!(out)?;
+    writeln!(out, "unused: {}", dead.len())?;
+    for confidence in [Confidence::High, Confidence::Medium, Confidence::Low] {
+        let count = dead
+            .iter()
+            .filter(|finding| finding.confidence == confidence)
+            .count();
+        writeln!(out, "  {} confidence: {}", confidence.as_str(), count)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{project::LoadOptions, testing::TempDir};
+
+    const CORE: &str = r#"
+app = object()
+
+def unused():
+    pass
+
+def helper():
+    pass
+
+def only_used_by_unused():
+    pass
+
+def uses_unused():
+    only_used_by_unused()
+
+def worker():
+    pass
+
+HANDLERS = {"work": worker}
+
+@app.route("/")
+def index():
+    helper()
+
+class Base:
+    def run(self):
+        pass
+
+class Child(Base):
+    def run(self):
+        pass
+
+class Thing:
+    def called_on_a_variable(self):
+        pass
+
+    def __repr__(self):
+        return "thing"
+
+def call(x):
+    x.called_on_a_variable()
+"#;
+
+    const CLI: &str = r#"
+from pkg.core import Child, Thing, call
+
+if __name__ == "__main__":
+    Child()
+    call(Thing())
+"#;
+
+    // The unused code in a small project, by name, with how sure pydep is
+    fn dead_code(name: &str, configured: &[String]) -> Vec<(String, Confidence)> {
+        let dir = TempDir::new(name);
+        dir.write(
+            "pyproject.toml",
+            "[project]\nname = \"pkg\"\n\n[project.scripts]\npkg = \"pkg.scripts:main\"\n",
+        );
+        dir.write("pkg/__init__.py", "");
+        dir.write("pkg/core.py", CORE);
+        dir.write("pkg/cli.py", CLI);
+        dir.write("pkg/scripts.py", "def main():\n    pass\n");
+        dir.write("pkg/plugins.py", "def plugin():\n    pass\n");
+        dir.write(
+            "tests/test_core.py",
+            "def make_thing():\n    pass\n\ndef test_thing():\n    pass\n",
+        );
+        let project = Project::load(&dir.path, &LoadOptions::default()).unwrap();
+        let entry_points = find_entry_points(&project, configured).unwrap();
+        find_dead_code(&project, &entry_points)
+            .iter()
+            .map(|finding| {
+                let fqn = project.graph.node(finding.node).fqn.to_string();
+                (fqn, finding.confidence)
+            })
+            .collect()
+    }
+
+    fn found(dead: &[(&str, Confidence)]) -> Vec<(String, Confidence)> {
+        dead.iter()
+            .map(|&(name, confidence)| (name.to_string(), confidence))
+            .collect()
+    }
+
+    #[test]
+    fn unused_code_is_found_with_its_confidence() {
+        // main blocks, console scripts, tests, framework decorators, and
+        // configured entry points all keep what they use alive
+        let configured = ["pkg.plugins::plugin".to_string()];
+        assert_eq!(
+            dead_code("dead-code", &configured),
+            found(&[
+                ("pkg.core::unused", Confidence::High),
+                ("pkg.core::only_used_by_unused", Confidence::Medium),
+                ("pkg.core::uses_unused", Confidence::High),
+                ("pkg.core::worker", Confidence::Low),
+                ("pkg.core::Base.run", Confidence::High),
+                ("pkg.core::Child.run", Confidence::Low),
+                ("pkg.core::Thing.called_on_a_variable", Confidence::Low),
+            ])
+        );
+    }
+
+    #[test]
+    fn unconfigured_entry_points_are_unused() {
+        let dead = dead_code("dead-code-unconfigured", &[]);
+        assert!(dead.contains(&("pkg.plugins::plugin".to_string(), Confidence::High)));
+    }
+
+    #[test]
+    fn whitelist_globs_match_fully_qualified_names() {
+        let dir = TempDir::new("dead-code-whitelist");
+        let path = dir.write(
+            "whitelist.txt",
+            "# accepted\npkg.core::Base.*\npkg.plugins::plugin\n",
+        );
+        let whitelist = Whitelist::load(&path).unwrap();
+        assert!(whitelist.contains("pkg.core::Base.run"));
+        assert!(whitelist.contains("pkg.plugins::plugin"));
+        assert!(!whitelist.contains("pkg.core::Child.run"));
+        assert!(!whitelist.contains("pkg.plugins::plugin_two"));
+    }
+
+    #[test]
+    fn test_dirs_outside_the_roots_are_found() {
+        let dir = TempDir::new("dead-code-src-layout");
+        dir.write("src/pkg/__init__.py", "");
+        dir.write("tests/test_pkg.py", "");
+        let load = |roots: &[&str]| {
+            let options = LoadOptions {
+                roots: roots.iter().map(PathBuf::from).collect(),
+                ..LoadOptions::default()
+            };
+            Project::load(&dir.path, &options).unwrap()
+        };
+        assert_eq!(
+            find_unparsed_test_dirs(&load(&["src"])),
+            [dir.path.join("tests")]
+        );
+        assert!(find_unparsed_test_dirs(&load(&["src", "tests"])).is_empty());
+    }
+}
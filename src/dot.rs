@@ -0,0 +1,101 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+};
+
+use crate::graph::{DependencyGraph, EdgeKind, Node, NodeKind, Selection};
+
+pub fn write_dot(
+    out: &mut impl Write,
+    graph: &DependencyGraph,
+    selection: &Selection,
+    root: Option<&str>,
+) -> io::Result<()> {
+    writeln!(out, "digraph pydep {{")?;
+    writeln!(out, "    rankdir=LR;")?;
+    writeln!(out, "    node [shape=box, fontname=\"Helvetica\"];")?;
+
+    // project nodes are clustered by package, external ones are left loose
+    let mut packages: BTreeMap<&str, Vec<&Node>> = BTreeMap::new();
+    let mut loose = vec![];
+    for &id in &selection.nodes {
+        let node = graph.node(id);
+        match node.package() {
+            Some(package) if !node.external => packages.entry(package).or_default().push(node),
+            _ => loose.push(node),
+        }
+    }
+
+    for (index, (package, nodes)) in packages.iter().enumerate() {
+        writeln!(
+            out,
+            "    subgraph cluster_{}_{} {{",
+            index,
+            package.replace(|c: char| !c.is_alphanumeric(), "_")
+        )?;
+        writeln!(out, "        label={};", quote(package))?;
+        writeln!(out, "        style=rounded;")?;
+        for node in nodes {
+            write_node(out, "        ", node, root)?;
+        }
+        writeln!(out, "    }}")?;
+    }
+    for node in loose {
+        write_node(out, "    ", node, root)?;
+    }
+
+    for &index in &selection.edges {
+        let edge = &graph.edges()[index];
+        writeln!(
+            out,
+            "    {} -> {} [{}];",
+            quote(&graph.node(edge.from).fqn),
+            quote(&graph.node(edge.to).fqn),
+            edge_style(edge.kind)
+        )?;
+    }
+    writeln!(out, "}}")
+}
+
+fn write_node(
+    out: &mut impl Write,
+    indent: &str,
+    node: &Node,
+    root: Option<&str>,
+) -> io::Result<()> {
+    let mut attributes = vec![format!("label={}", quote(&node.fqn))];
+    match node.kind {
+        NodeKind::Module => attributes.push("shape=folder".to_string()),
+        NodeKind::Class => attributes.push("shape=component".to_string()),
+        _ => {}
+    }
+    if node.external {
+        attributes.push("style=dashed, color=gray50, fontcolor=gray50".to_string());
+    }
+    if Some(node.fqn.as_str()) == root {
+        attributes.push("penwidth=2".to_string());
+    }
+    writeln!(
+        out,
+        "{}{} [{}];",
+        indent,
+        quote(&node.fqn),
+        attributes.join(", ")
+    )
+}
+
+// Styles only, no labels, since labels on every edge make large graphs unreadable
+fn edge_style(kind: EdgeKind) -> &'static str {
+    match kind {
+        EdgeKind::Import => "color=black",
+        EdgeKind::Call => "color=blue",
+        EdgeKind::Inherit => "color=black, arrowhead=empty",
+        EdgeKind::Instantiate => "color=darkgreen, style=dashed",
+        EdgeKind::Decorate => "color=purple, style=dotted",
+        EdgeKind::Annotate => "color=gray50, style=dotted",
+    }
+}
+
+fn quote(id: &str) -> String {
+    format!("\"{}\"", id.replace('\\', "\\\\").replace('"', "\\\""))
+}
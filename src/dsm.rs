@@ -0,0 +1,166 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{self, Write},
+};
+
+use crate::{
+    graph::{strongly_connected_components, DependencyGraph, EdgeKind},
+    table::Table,
+};
+
+/// A package by package dependency structure matrix. `counts[row][column]`
+/// is how many imports in package `row` are of modules in package `column`
+pub struct Dsm {
+    pub packages: Vec<String>,
+    pub counts: Vec<Vec<usize>>,
+}
+
+impl Dsm {
+    /// Count the imports between the project's packages, ordering them so each
+    /// package comes after the packages it depends on. Packages that depend on
+    /// each other are kept together, so cycles show up as blocks of counts
+    /// above the diagonal
+    pub fn build(graph: &DependencyGraph) -> Dsm {
+        let package_of = |id| {
+            let node = graph.node(id);
+            node.package().unwrap_or(&node.module).to_string()
+        };
+
+        let mut names = BTreeSet::new();
+        let mut imports: BTreeMap<(String, String), usize> = BTreeMap::new();
+        for (id, node) in graph.nodes().iter().enumerate() {
+            if !node.external {
+                names.insert(package_of(id));
+            }
+        }
+        for edge in graph.edges() {
+            if edge.kind != EdgeKind::Import || graph.node(edge.to).external {
+                continue;
+            }
+            let from = package_of(edge.from);
+            let to = package_of(edge.to);
+            if from != to {
+                *imports.entry((from, to)).or_default() += 1;
+            }
+        }
+
+        let names: Vec<String> = names.into_iter().collect();
+        let position = |name: &str| names.binary_search_by(|other| other.as_str().cmp(name));
+        let mut successors = vec![vec![]; names.len()];
+        for (from, to) in imports.keys() {
+            successors[position(from).unwrap()].push(position(to).unwrap());
+        }
+        let order: Vec<usize> = strongly_connected_components(&successors)
+            .into_iter()
+            .flatten()
+            .collect();
+
+        let packages: Vec<String> = order.iter().map(|&i| names[i].to_string()).collect();
+        let counts = packages
+            .iter()
+            .map(|row| {
+                packages
+                    .iter()
+                    .map(|column| {
+                        imports
+                            .get(&(row.to_string(), column.to_string()))
+                            .copied()
+                            .unwrap_or(0)
+                    })
+                    .collect()
+            })
+            .collect();
+        Dsm { packages, counts }
+    }
+
+    /// Write the matrix with numbered rows and columns. Empty cells are `.`
+    /// and the diagonal is `-`
+    pub fn write_text(&self, out: &mut impl Write) -> io::Result<()> {
+        let numbers: Vec<String> = (1..=self.packages.len()).map(|n| n.to_string()).collect();
+        let mut header = vec!["", "package"];
+        header.extend(numbers.iter().map(String::as_str));
+        let mut table = Table::new(&header).align_right(0);
+        for column in 0..self.packages.len() {
+            table = table.align_right(column + 2);
+        }
+        for (row, package) in self.packages.iter().enumerate() {
+            let mut cells = vec![numbers[row].to_string(), package.to_string()];
+            cells.extend(
+                self.counts[row]
+                    .iter()
+                    .enumerate()
+                    .map(|(column, &count)| match count {
+                        _ if row == column => "-".to_string(),
+                        0 => ".".to_string(),
+                        count => count.to_string(),
+                    }),
+            );
+            table.push(cells);
+        }
+        table.write_text(out)
+    }
+
+    /// Write the matrix as CSV, with packages as the row and column labels
+    /// and empty cells for no imports
+    pub fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut header = vec!["package"];
+        header.extend(self.packages.iter().map(String::as_str));
+        let mut table = Table::new(&header);
+        for (row, package) in self.packages.iter().enumerate() {
+            let mut cells = vec![package.to_string()];
+            cells.extend(self.counts[row].iter().map(|&count| match count {
+                0 => String::new(),
+                count => count.to_string(),
+            }));
+            table.push(cells);
+        }
+        table.write_csv(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::build_graph;
+
+    fn dsm() -> Dsm {
+        Dsm::build(&build_graph(&[
+            ("z/x.py", ""),
+            ("y/x.py", "import z.x\n"),
+            ("c/x.py", "import d.x\n"),
+            ("d/x.py", "import y.x\nimport c.x\n"),
+            ("d/y.py", "import c.x\n"),
+        ]))
+    }
+
+    #[test]
+    fn dependencies_come_first_and_cycles_stay_together() {
+        let dsm = dsm();
+        assert_eq!(dsm.packages, ["z", "y", "c", "d"]);
+        assert_eq!(
+            dsm.counts,
+            [[0, 0, 0, 0], [1, 0, 0, 0], [0, 0, 0, 1], [0, 1, 2, 0]]
+        );
+
+        let mut out = Vec::new();
+        dsm.write_text(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "   package  1  2  3  4\n\
+             1  z        -  .  .  .\n\
+             2  y        1  -  .  .\n\
+             3  c        .  .  -  1\n\
+             4  d        .  1  2  -\n"
+        );
+    }
+
+    #[test]
+    fn csv_leaves_empty_cells_blank() {
+        let mut out = Vec::new();
+        dsm().write_csv(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "package,z,y,c,d\nz,,,,\ny,1,,,\nc,,,,1\nd,,1,2,\n"
+        );
+    }
+}
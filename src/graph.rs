@@ -0,0 +1,1131 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    path::PathBuf,
+};
+
+use serde::Serialize;
+
+use crate::{
+    project::{Import, ImportFrom, Location, PyFunction, PyModule, Reference},
+    tree::{DependencyTree, TreeOptions},
+};
+
+/// Index of a node in a [`DependencyGraph`]
+pub type NodeId = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeKind {
+    Module,
+    Class,
+    Function,
+    Method,
+    /// A name imported from a module outside the project. It may be a
+    /// function, a class, or anything else
+    Symbol,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EdgeKind {
+    Import,
+    Call,
+    Inherit,
+    Instantiate,
+    Decorate,
+    Annotate,
+}
+
+impl NodeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NodeKind::Module => "module",
+            NodeKind::Class => "class",
+            NodeKind::Function => "function",
+            NodeKind::Method => "method",
+            NodeKind::Symbol => "symbol",
+        }
+    }
+}
+
+impl EdgeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EdgeKind::Import => "import",
+            EdgeKind::Call => "call",
+            EdgeKind::Inherit => "inherit",
+            EdgeKind::Instantiate => "instantiate",
+            EdgeKind::Decorate => "decorate",
+            EdgeKind::Annotate => "annotate",
+        }
+    }
+}
+
+/// Edges followed when tracing the dependencies of a module
+pub const MODULE_EDGES: &[EdgeKind] = &[EdgeKind::Import];
+
+/// Edges followed when tracing the dependencies of a function or method
+pub const FUNCTION_EDGES: &[EdgeKind] = &[EdgeKind::Call, EdgeKind::Instantiate];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Node {
+    /// Fully qualified name, like `package.module`, `package.module::function`
+    /// or `package.module::Class.method`
+    pub fqn: String,
+    pub kind: NodeKind,
+    /// The module the node is defined in, which for a module is itself
+    pub module: String,
+    /// Whether the node is outside the project
+    pub external: bool,
+    pub path: Option<PathBuf>,
+    pub location: Option<Location>,
+    /// Lines of source the definition spans, or the whole file for a module
+    pub lines: Option<usize>,
+}
+
+impl Node {
+    /// The package the node's module is in. A package's __init__.py counts
+    /// as being in the package itself
+    pub fn package(&self) -> Option<&str> {
+        let is_init = self
+            .path
+            .as_ref()
+            .and_then(|path| path.file_stem())
+            .is_some_and(|stem| stem == "__init__");
+        if is_init {
+            Some(&self.module)
+        } else {
+            self.module.rsplit_once('.').map(|(package, _)| package)
+        }
+    }
+}
+
+/// A dependency of one node on another, found at `location` in the file of
+/// the `from` node
+#[derive(Debug, Clone, Serialize)]
+pub struct Edge {
+    pub from: NodeId,
+    pub to: NodeId,
+    pub kind: EdgeKind,
+    pub location: Location,
+    /// An import under `if TYPE_CHECKING:`, which never runs
+    pub type_checking: bool,
+}
+
+/// A call made by a node that pydep couldn't match to anything it knows
+#[derive(Debug, Clone, Serialize)]
+pub struct UnresolvedCall {
+    pub from: NodeId,
+    /// The name called, as written
+    pub name: String,
+    pub location: Location,
+    pub reason: UnresolvedReason,
+}
+
+/// A guess at why a call wasn't resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UnresolvedReason {
+    /// A python builtin like `print` or `str.join`
+    Builtin,
+    /// An attribute of something pydep can't follow, like a local variable,
+    /// a parameter, or `self.attribute.method()`
+    DynamicAttribute,
+    /// A plain name that isn't defined or imported in the module
+    UnknownLocal,
+}
+
+impl UnresolvedReason {
+    fn of(name: &str) -> UnresolvedReason {
+        let first = name.split('.').next().unwrap_or(name);
+        if PYTHON_BUILTINS
+            .split_whitespace()
+            .any(|builtin| builtin == first)
+        {
+            UnresolvedReason::Builtin
+        } else if name.contains('.') {
+            UnresolvedReason::DynamicAttribute
+        } else {
+            UnresolvedReason::UnknownLocal
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UnresolvedReason::Builtin => "builtin",
+            UnresolvedReason::DynamicAttribute => "dynamic attribute",
+            UnresolvedReason::UnknownLocal => "unknown local",
+        }
+    }
+}
+
+// Names python provides without an import
+const PYTHON_BUILTINS: &str = "\
+    abs aiter all anext any ascii bin bool breakpoint bytearray bytes callable chr \
+    classmethod compile complex delattr dict dir divmod enumerate eval exec filter float \
+    format frozenset getattr globals hasattr hash help hex id input int isinstance \
+    issubclass iter len list locals map max memoryview min next object oct open ord pow \
+    print property range repr reversed round set setattr slice sorted staticmethod str \
+    sum super tuple type vars zip __import__ BaseException Exception ArithmeticError \
+    AssertionError AttributeError EOFError ImportError IndexError KeyError \
+    KeyboardInterrupt LookupError MemoryError NameError NotImplementedError OSError \
+    IOError OverflowError RecursionError RuntimeError StopIteration SyntaxError \
+    SystemExit TypeError UnicodeError ValueError ZeroDivisionError FileNotFoundError \
+    PermissionError TimeoutError ConnectionError DeprecationWarning UserWarning Warning";
+
+/// Some of the nodes of a graph and edges between them, in the order they
+/// were reached
+#[derive(Debug, Default)]
+pub struct Selection {
+    pub nodes: Vec<NodeId>,
+    /// Indexes into [`DependencyGraph::edges`]
+    pub edges: Vec<usize>,
+}
+
+/// Every module, class, function, and method in a project, and the
+/// dependencies between them
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+    ids: HashMap<String, NodeId>,
+    outgoing: Vec<Vec<usize>>,
+    incoming: Vec<Vec<usize>>,
+    unresolved: Vec<UnresolvedCall>,
+}
+
+impl DependencyGraph {
+    pub fn build(modules: &BTreeMap<String, PyModule>) -> DependencyGraph {
+        let mut builder = GraphBuilder {
+            graph: DependencyGraph::default(),
+            modules,
+            classes_by_name: HashMap::new(),
+        };
+        builder.add_definitions();
+        // class hierarchies come first so methods can be found on base classes
+        // while resolving calls
+        let mut scopes = vec![];
+        for module in modules.values() {
+            let scope = builder.build_scope(module);
+            builder.add_bases(module, &scope);
+            scopes.push(scope);
+        }
+        for (module, mut scope) in modules.values().zip(scopes) {
+            builder.add_dependencies(module, &mut scope);
+        }
+
+        let mut graph = builder.graph;
+        let edges = &graph.edges;
+        for outgoing in &mut graph.outgoing {
+            outgoing.sort_by_key(|&edge| edges[edge].location);
+        }
+        graph
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id]
+    }
+
+    pub fn find(&self, fqn: &str) -> Option<NodeId> {
+        self.ids.get(fqn).copied()
+    }
+
+    /// Calls that didn't match anything, in the order modules were visited
+    pub fn unresolved_calls(&self) -> &[UnresolvedCall] {
+        &self.unresolved
+    }
+
+    /// Edges from a node to the nodes it depends on, in source order
+    pub fn dependencies(&self, id: NodeId) -> impl Iterator<Item = &Edge> {
+        self.outgoing[id].iter().map(|&edge| &self.edges[edge])
+    }
+
+    /// The whole graph
+    pub fn select_all(&self) -> Selection {
+        Selection {
+            nodes: (0..self.nodes.len()).collect(),
+            edges: (0..self.edges.len()).collect(),
+        }
+    }
+
+    /// Everything a node depends on, directly or transitively, following
+    /// only edges of the given kinds
+    pub fn select_reachable(&self, root: NodeId, kinds: &[EdgeKind]) -> Selection {
+        let mut selection = Selection::default();
+        let mut seen = HashSet::from([root]);
+        let mut queue = VecDeque::from([root]);
+        while let Some(id) = queue.pop_front() {
+            selection.nodes.push(id);
+            for &index in &self.outgoing[id] {
+                let edge = &self.edges[index];
+                if !kinds.contains(&edge.kind) {
+                    continue;
+                }
+                selection.edges.push(index);
+                if seen.insert(edge.to) {
+                    queue.push_back(edge.to);
+                }
+            }
+        }
+        selection
+    }
+
+    /// Edges to a node from the nodes that depend on it
+    pub fn dependents(&self, id: NodeId) -> impl Iterator<Item = &Edge> {
+        self.incoming[id].iter().map(|&edge| &self.edges[edge])
+    }
+
+    /// The fewest edges of the given kinds leading from one node to another,
+    /// as indexes into [`DependencyGraph::edges`]
+    pub fn shortest_path(
+        &self,
+        from: NodeId,
+        to: NodeId,
+        kinds: &[EdgeKind],
+    ) -> Option<Vec<usize>> {
+        let mut reached_by: HashMap<NodeId, usize> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        while let Some(id) = queue.pop_front() {
+            if id == to {
+                let mut path = vec![];
+                let mut id = to;
+                while id != from {
+                    let edge = reached_by[&id];
+                    path.push(edge);
+                    id = self.edges[edge].from;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for &index in &self.outgoing[id] {
+                let edge = &self.edges[index];
+                if kinds.contains(&edge.kind)
+                    && edge.to != from
+                    && !reached_by.contains_key(&edge.to)
+                {
+                    reached_by.insert(edge.to, index);
+                    queue.push_back(edge.to);
+                }
+            }
+        }
+        None
+    }
+
+    /// Paths of edges of the given kinds from one node to another that don't
+    /// visit any node twice, stopping after `limit` of them. Two nodes joined
+    /// by several edges count as one step, taken by the first edge in source
+    /// order. Shorter paths come first
+    pub fn simple_paths(
+        &self,
+        from: NodeId,
+        to: NodeId,
+        kinds: &[EdgeKind],
+        limit: usize,
+    ) -> Vec<Vec<usize>> {
+        // the fewest steps from each node that can reach the target to it.
+        // Other nodes aren't worth walking into
+        let mut distance = HashMap::from([(to, 0)]);
+        let mut queue = VecDeque::from([to]);
+        while let Some(id) = queue.pop_front() {
+            let steps = distance[&id] + 1;
+            for &index in &self.incoming[id] {
+                let edge = &self.edges[index];
+                if kinds.contains(&edge.kind) && !distance.contains_key(&edge.from) {
+                    distance.insert(edge.from, steps);
+                    queue.push_back(edge.from);
+                }
+            }
+        }
+
+        let mut search = PathSearch {
+            graph: self,
+            to,
+            kinds,
+            length: 0,
+            distance,
+            path: vec![],
+            on_path: HashSet::from([from]),
+            paths: vec![],
+        };
+        // look for paths one length at a time, so every shorter path is found
+        // before a longer one. A simple path visits each node at most once
+        if let Some(&shortest) = search.distance.get(&from) {
+            for length in shortest.max(1)..search.distance.len() {
+                if search.paths.len() >= limit {
+                    break;
+                }
+                search.length = length;
+                search.walk(from);
+            }
+        }
+        search.paths.truncate(limit);
+        search.paths
+    }
+
+    /// The transitive dependencies of a node, following only edges of the
+    /// given kinds. A node that depends on one of its own ancestors is listed
+    /// without expanding it again
+    pub fn dependency_tree(
+        &self,
+        root: NodeId,
+        kinds: &[EdgeKind],
+        options: &TreeOptions,
+    ) -> DependencyTree {
+        let mut tree = DependencyTree::new(self.nodes[root].fqn.to_string());
+        let mut walk = TreeWalk {
+            kinds,
+            options,
+            ancestors: vec![],
+            expanded: HashMap::from([(root, 0)]),
+        };
+        self.add_dependencies_to_tree(&mut tree, root, &mut walk);
+        tree
+    }
+
+    fn add_dependencies_to_tree(&self, tree: &mut DependencyTree, id: NodeId, walk: &mut TreeWalk) {
+        if walk
+            .options
+            .max_depth
+            .is_some_and(|max_depth| walk.ancestors.len() >= max_depth)
+        {
+            return;
+        }
+        walk.ancestors.push(id);
+        let mut seen = HashSet::new();
+        for edge in self.dependencies(id) {
+            if !walk.kinds.contains(&edge.kind) || !seen.insert(edge.to) {
+                continue;
+            }
+            let mut child = DependencyTree::new(self.nodes[edge.to].fqn.to_string());
+            // a node listed deeper down may have been cut off by the depth
+            // limit, so it is only a repeat if it was listed at least as high
+            let depth = walk.ancestors.len();
+            let listed_above = walk.expanded.get(&edge.to).is_some_and(|&listed_depth| {
+                walk.options.max_depth.is_none() || listed_depth <= depth
+            });
+            if walk.options.elide_repeats && listed_above {
+                child.repeated = true;
+            } else if !walk.ancestors.contains(&edge.to) {
+                walk.expanded.insert(edge.to, depth);
+                self.add_dependencies_to_tree(&mut child, edge.to, walk);
+            }
+            tree.dependencies.push(child);
+        }
+        walk.ancestors.pop();
+    }
+
+    // Add a node, or find the one already there with the same name. Python
+    // lets a name be defined twice, like a property and its setter or the
+    // variants of a `typing.overload`, and they all count as the first one
+    fn add_node(&mut self, node: Node) -> NodeId {
+        if let Some(id) = self.find(&node.fqn) {
+            return id;
+        }
+        let id = self.nodes.len();
+        self.ids.insert(node.fqn.to_string(), id);
+        self.nodes.push(node);
+        self.outgoing.push(vec![]);
+        self.incoming.push(vec![]);
+        id
+    }
+
+    fn add_edge(&mut self, edge: Edge) {
+        let index = self.edges.len();
+        self.outgoing[edge.from].push(index);
+        self.incoming[edge.to].push(index);
+        self.edges.push(edge);
+    }
+}
+
+// State while building a DependencyGraph::dependency_tree
+struct TreeWalk<'a> {
+    kinds: &'a [EdgeKind],
+    options: &'a TreeOptions,
+    ancestors: Vec<NodeId>,
+    /// Nodes whose dependencies have been listed somewhere in the tree, and
+    /// the shallowest depth they were listed at
+    expanded: HashMap<NodeId, usize>,
+}
+
+// A depth first walk for DependencyGraph::simple_paths
+struct PathSearch<'a> {
+    graph: &'a DependencyGraph,
+    to: NodeId,
+    kinds: &'a [EdgeKind],
+    /// The number of edges in the paths being looked for
+    length: usize,
+    distance: HashMap<NodeId, usize>,
+    path: Vec<usize>,
+    on_path: HashSet<NodeId>,
+    paths: Vec<Vec<usize>>,
+}
+
+impl PathSearch<'_> {
+    fn walk(&mut self, id: NodeId) {
+        let graph = self.graph;
+        let mut seen = HashSet::new();
+        for &index in &graph.outgoing[id] {
+            let edge = &graph.edges[index];
+            if !self.kinds.contains(&edge.kind)
+                || self.on_path.contains(&edge.to)
+                || !seen.insert(edge.to)
+            {
+                continue;
+            }
+            // skip nodes that can't reach the target in the steps left
+            match self.distance.get(&edge.to) {
+                Some(&steps) if self.path.len() + 1 + steps <= self.length => {}
+                _ => continue,
+            }
+            self.path.push(index);
+            if edge.to == self.to {
+                if self.path.len() == self.length {
+                    self.paths.push(self.path.clone());
+                }
+            } else {
+                self.on_path.insert(edge.to);
+                self.walk(edge.to);
+                self.on_path.remove(&edge.to);
+            }
+            self.path.pop();
+        }
+    }
+}
+
+// What names refer to inside one module
+#[derive(Clone)]
+struct Scope {
+    bindings: HashMap<String, NodeId>,
+    /// Modules imported with a plain `import a.b.c`, which are used by their
+    /// full dotted name
+    imported_modules: HashSet<String>,
+    /// The class whose methods are being resolved, for `self.method()`
+    class: Option<NodeId>,
+}
+
+struct GraphBuilder<'a> {
+    graph: DependencyGraph,
+    modules: &'a BTreeMap<String, PyModule>,
+    classes_by_name: HashMap<String, Vec<NodeId>>,
+}
+
+impl GraphBuilder<'_> {
+    // Add a node for everything defined in the project, so dependencies can
+    // refer to them no matter what order modules are visited in
+    fn add_definitions(&mut self) {
+        for module in self.modules.values() {
+            for node in definitions(module) {
+                self.graph.add_node(node);
+            }
+            for class in &module.classes {
+                let class_id = self
+                    .graph
+                    .find(&format!("{}::{}", module.name, class.name))
+                    .unwrap();
+                let classes = self
+                    .classes_by_name
+                    .entry(class.name.to_string())
+                    .or_default();
+                if !classes.contains(&class_id) {
+                    classes.push(class_id);
+                }
+            }
+        }
+    }
+
+    fn add_bases(&mut self, module: &PyModule, scope: &Scope) {
+        for class in &module.classes {
+            let class_id = self
+                .graph
+                .find(&format!("{}::{}", module.name, class.name))
+                .unwrap();
+            for base in &class.bases {
+                self.add_reference(class_id, scope, base, EdgeKind::Inherit);
+            }
+        }
+    }
+
+    fn add_dependencies(&mut self, module: &PyModule, scope: &mut Scope) {
+        let module_id = self.graph.find(&module.name).unwrap();
+
+        for reference in &module.calls {
+            self.add_call(module_id, scope, reference);
+        }
+        for function in &module.functions {
+            let function_id = self
+                .graph
+                .find(&format!("{}::{}", module.name, function.name))
+                .unwrap();
+            self.add_function_dependencies(function_id, module, scope, function);
+        }
+        for class in &module.classes {
+            let class_id = self
+                .graph
+                .find(&format!("{}::{}", module.name, class.name))
+                .unwrap();
+            for decorator in &class.decorators {
+                self.add_reference(class_id, scope, decorator, EdgeKind::Decorate);
+            }
+            scope.class = Some(class_id);
+            for method in &class.methods {
+                let method_id = self
+                    .graph
+                    .find(&format!("{}::{}.{}", module.name, class.name, method.name))
+                    .unwrap();
+                self.add_function_dependencies(method_id, module, scope, method);
+            }
+            scope.class = None;
+        }
+    }
+
+    // Bind every imported and locally defined name, adding an import edge for
+    // each imported module along the way
+    fn build_scope(&mut self, module: &PyModule) -> Scope {
+        let module_id = self.graph.find(&module.name).unwrap();
+        let mut scope = Scope {
+            bindings: HashMap::new(),
+            imported_modules: HashSet::new(),
+            class: None,
+        };
+
+        self.add_imports(
+            module_id,
+            module,
+            &module.imports,
+            &module.import_froms,
+            &mut scope,
+        );
+
+        // local definitions shadow imports
+        for function in &module.functions {
+            let fqn = format!("{}::{}", module.name, function.name);
+            scope
+                .bindings
+                .insert(function.name.to_string(), self.graph.find(&fqn).unwrap());
+        }
+        for class in &module.classes {
+            let fqn = format!("{}::{}", module.name, class.name);
+            scope
+                .bindings
+                .insert(class.name.to_string(), self.graph.find(&fqn).unwrap());
+        }
+
+        scope
+    }
+
+    // Bind the names a block of imports brings in, adding an import edge from
+    // `from` for each imported module
+    fn add_imports(
+        &mut self,
+        from: NodeId,
+        module: &PyModule,
+        imports: &[Import],
+        import_froms: &[ImportFrom],
+        scope: &mut Scope,
+    ) {
+        for import in imports {
+            let imported_id = self.module_node(&import.module);
+            self.graph.add_edge(Edge {
+                from,
+                to: imported_id,
+                kind: EdgeKind::Import,
+                location: import.location,
+                type_checking: import.type_checking,
+            });
+            match &import.alias {
+                Some(alias) => {
+                    scope.bindings.insert(alias.to_string(), imported_id);
+                }
+                None => {
+                    scope.imported_modules.insert(import.module.to_string());
+                }
+            }
+        }
+
+        for import_from in import_froms {
+            let Some(base) =
+                resolve_relative_import(module, &import_from.module, import_from.level)
+            else {
+                continue;
+            };
+            let mut imported_ids = vec![];
+            for imported_name in &import_from.names {
+                if imported_name.name == "*" {
+                    if let Some(imported) = self.modules.get(&base) {
+                        for function in &imported.functions {
+                            let fqn = format!("{}::{}", base, function.name);
+                            if let Some(id) = self.graph.find(&fqn) {
+                                scope.bindings.insert(function.name.to_string(), id);
+                            }
+                        }
+                        for class in &imported.classes {
+                            let fqn = format!("{}::{}", base, class.name);
+                            if let Some(id) = self.graph.find(&fqn) {
+                                scope.bindings.insert(class.name.to_string(), id);
+                            }
+                        }
+                    }
+                    imported_ids.push(self.module_node(&base));
+                    continue;
+                }
+
+                // `from package import submodule` imports the submodule itself
+                let submodule = format!("{}.{}", base, imported_name.name);
+                let (imported_id, target) = if self.modules.contains_key(&submodule) {
+                    let id = self.module_node(&submodule);
+                    (id, Some(id))
+                } else {
+                    (
+                        self.module_node(&base),
+                        self.resolve_export(&base, &imported_name.name, 0),
+                    )
+                };
+                if !imported_ids.contains(&imported_id) {
+                    imported_ids.push(imported_id);
+                }
+                if let Some(target) = target {
+                    scope
+                        .bindings
+                        .insert(imported_name.bound_name().to_string(), target);
+                }
+            }
+            for imported_id in imported_ids {
+                self.graph.add_edge(Edge {
+                    from,
+                    to: imported_id,
+                    kind: EdgeKind::Import,
+                    location: import_from.location,
+                    type_checking: import_from.type_checking,
+                });
+            }
+        }
+    }
+
+    // Find what `from module import name` refers to, following names that a
+    // module itself imports from elsewhere, like a package re-exporting from
+    // its __init__.py
+    fn resolve_export(&mut self, module_name: &str, name: &str, depth: usize) -> Option<NodeId> {
+        let Some(module) = self.modules.get(module_name) else {
+            return Some(self.symbol_node(module_name, name));
+        };
+        if let Some(id) = self.graph.find(&format!("{}::{}", module_name, name)) {
+            return Some(id);
+        }
+        if depth > 8 {
+            return None;
+        }
+        for import_from in &module.import_froms {
+            let Some(imported_name) = import_from
+                .names
+                .iter()
+                .find(|imported_name| imported_name.bound_name() == name)
+            else {
+                continue;
+            };
+            let base = resolve_relative_import(module, &import_from.module, import_from.level)?;
+            let submodule = format!("{}.{}", base, imported_name.name);
+            if self.modules.contains_key(&submodule) {
+                return self.graph.find(&submodule);
+            }
+            return self.resolve_export(&base, &imported_name.name, depth + 1);
+        }
+        None
+    }
+
+    fn add_function_dependencies(
+        &mut self,
+        id: NodeId,
+        module: &PyModule,
+        scope: &Scope,
+        function: &PyFunction,
+    ) {
+        // names imported inside the function shadow the module's own
+        let local_scope;
+        let scope = if function.imports.is_empty() && function.import_froms.is_empty() {
+            scope
+        } else {
+            let mut scope = scope.clone();
+            self.add_imports(
+                id,
+                module,
+                &function.imports,
+                &function.import_froms,
+                &mut scope,
+            );
+            local_scope = scope;
+            &local_scope
+        };
+        for decorator in &function.decorators {
+            self.add_reference(id, scope, decorator, EdgeKind::Decorate);
+        }
+        for annotation in &function.annotations {
+            self.add_reference(id, scope, annotation, EdgeKind::Annotate);
+        }
+        for call in &function.calls {
+            self.add_call(id, scope, call);
+        }
+    }
+
+    fn add_call(&mut self, from: NodeId, scope: &Scope, call: &Reference) {
+        let target = if call.lookup {
+            self.resolve(scope, &call.name)
+                .or_else(|| match self.classes_by_name.get(&call.name) {
+                    Some(classes) if classes.len() == 1 => Some(classes[0]),
+                    _ => None,
+                })
+        } else {
+            self.resolve(scope, &call.name)
+        };
+        let Some(to) = target else {
+            self.graph.unresolved.push(UnresolvedCall {
+                from,
+                name: call.name.to_string(),
+                location: call.location,
+                reason: UnresolvedReason::of(&call.name),
+            });
+            return;
+        };
+        let kind = match self.graph.nodes[to].kind {
+            NodeKind::Module => return,
+            NodeKind::Class => EdgeKind::Instantiate,
+            _ => EdgeKind::Call,
+        };
+        self.graph.add_edge(Edge {
+            from,
+            to,
+            kind,
+            location: call.location,
+            type_checking: false,
+        });
+    }
+
+    fn add_reference(
+        &mut self,
+        from: NodeId,
+        scope: &Scope,
+        reference: &Reference,
+        kind: EdgeKind,
+    ) {
+        if let Some(to) = self.resolve(scope, &reference.name) {
+            if self.graph.nodes[to].kind != NodeKind::Module {
+                self.graph.add_edge(Edge {
+                    from,
+                    to,
+                    kind,
+                    location: reference.location,
+                    type_checking: false,
+                });
+            }
+        }
+    }
+
+    fn resolve(&mut self, scope: &Scope, name: &str) -> Option<NodeId> {
+        if let Some(&id) = scope.bindings.get(name) {
+            return Some(id);
+        }
+        let parts: Vec<&str> = name.split('.').collect();
+        if let (Some(class), ["self" | "cls", method]) = (scope.class, parts.as_slice()) {
+            return self.resolve_member(class, &[method]);
+        }
+        // the longest prefix that's bound to something, like `np` in
+        // `np.linalg.norm` or `a.b` after `import a.b`
+        for split in (1..parts.len()).rev() {
+            let prefix = parts[..split].join(".");
+            if let Some(&id) = scope.bindings.get(&prefix) {
+                return self.resolve_member(id, &parts[split..]);
+            }
+            if scope.imported_modules.contains(&prefix) {
+                let id = self.module_node(&prefix);
+                return self.resolve_member(id, &parts[split..]);
+            }
+        }
+        None
+    }
+
+    // Find an attribute of a module, class or external symbol
+    fn resolve_member(&mut self, id: NodeId, parts: &[&str]) -> Option<NodeId> {
+        if parts.is_empty() {
+            return Some(id);
+        }
+        let node = &self.graph.nodes[id];
+        let fqn = node.fqn.to_string();
+        match (node.kind, node.external) {
+            (NodeKind::Module, true) => Some(self.symbol_node(&fqn, &parts.join("."))),
+            (NodeKind::Module, false) => {
+                for split in (1..=parts.len()).rev() {
+                    let submodule = format!("{}.{}", fqn, parts[..split].join("."));
+                    if self.modules.contains_key(&submodule) {
+                        let submodule_id = self.module_node(&submodule);
+                        return self.resolve_member(submodule_id, &parts[split..]);
+                    }
+                }
+                self.graph.find(&format!("{}::{}", fqn, parts.join(".")))
+            }
+            (NodeKind::Class, _) if parts.len() == 1 => self.find_method(id, parts[0], 0),
+            (NodeKind::Symbol, _) => {
+                let (module, symbol) = fqn.split_once("::")?;
+                Some(self.symbol_node(module, &format!("{}.{}", symbol, parts.join("."))))
+            }
+            _ => None,
+        }
+    }
+
+    // Look up a method on a class, or on the classes it inherits from
+    fn find_method(&self, class: NodeId, method: &str, depth: usize) -> Option<NodeId> {
+        let fqn = format!("{}.{}", self.graph.nodes[class].fqn, method);
+        if let Some(id) = self.graph.find(&fqn) {
+            return Some(id);
+        }
+        if depth > 16 {
+            return None;
+        }
+        self.graph
+            .dependencies(class)
+            .filter(|edge| edge.kind == EdgeKind::Inherit)
+            .find_map(|edge| match self.graph.nodes[edge.to].kind {
+                NodeKind::Class => self.find_method(edge.to, method, depth + 1),
+                _ => None,
+            })
+    }
+
+    fn module_node(&mut self, name: &str) -> NodeId {
+        self.graph.find(name).unwrap_or_else(|| {
+            self.graph.add_node(Node {
+                fqn: name.to_string(),
+                kind: NodeKind::Module,
+                module: name.to_string(),
+                external: true,
+                path: None,
+                location: None,
+                lines: None,
+            })
+        })
+    }
+
+    fn symbol_node(&mut self, module: &str, name: &str) -> NodeId {
+        let fqn = format!("{}::{}", module, name);
+        self.graph.find(&fqn).unwrap_or_else(|| {
+            self.graph.add_node(Node {
+                fqn,
+                kind: NodeKind::Symbol,
+                module: module.to_string(),
+                external: true,
+                path: None,
+                location: None,
+                lines: None,
+            })
+        })
+    }
+}
+
+/// The nodes for a module and everything defined in it, as they go in the
+/// graph. A name defined more than once is only listed the first time
+pub fn definitions(module: &PyModule) -> Vec<Node> {
+    let definition = |fqn: String, kind: NodeKind, location: Option<Location>, lines: usize| Node {
+        fqn,
+        kind,
+        module: module.name.to_string(),
+        external: false,
+        path: Some(module.path.to_path_buf()),
+        location,
+        lines: Some(lines),
+    };
+    let mut nodes = vec![definition(
+        module.name.to_string(),
+        NodeKind::Module,
+        None,
+        module.lines,
+    )];
+    for function in &module.functions {
+        nodes.push(definition(
+            format!("{}::{}", module.name, function.name),
+            NodeKind::Function,
+            Some(function.location),
+            function.lines,
+        ));
+    }
+    for class in &module.classes {
+        nodes.push(definition(
+            format!("{}::{}", module.name, class.name),
+            NodeKind::Class,
+            Some(class.location),
+            class.lines,
+        ));
+        for method in &class.methods {
+            nodes.push(definition(
+                format!("{}::{}.{}", module.name, class.name, method.name),
+                NodeKind::Method,
+                Some(method.location),
+                method.lines,
+            ));
+        }
+    }
+    let mut seen = HashSet::new();
+    nodes.retain(|node| seen.insert(node.fqn.clone()));
+    nodes
+}
+
+/// The absolute name of the module in `from <dots><module> import ...`
+pub fn resolve_relative_import(importer: &PyModule, module: &str, level: usize) -> Option<String> {
+    if level == 0 {
+        return Some(module.to_string());
+    }
+    // a package's __init__ is relative to the package itself, anything else to
+    // the package containing it
+    let mut parts: Vec<&str> = importer.name.split('.').collect();
+    if !importer.is_package() {
+        parts.pop();
+    }
+    for _ in 1..level {
+        parts.pop()?;
+    }
+    if !module.is_empty() {
+        parts.push(module);
+    }
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join("."))
+    }
+}
+
+/// The strongly connected components of a graph given as lists of
+/// successors, with each component coming after every component it has an
+/// edge to. Nodes in a cycle share a component, and every other node is in a
+/// component of its own
+pub fn strongly_connected_components(successors: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    // Tarjan's algorithm, with an explicit stack so deep graphs can't
+    // overflow the real one
+    const UNVISITED: usize = usize::MAX;
+    let count = successors.len();
+    let mut index = vec![UNVISITED; count];
+    let mut low_link = vec![0; count];
+    let mut on_stack = vec![false; count];
+    let mut stack = vec![];
+    let mut components = vec![];
+    let mut next_index = 0;
+
+    for start in 0..count {
+        if index[start] != UNVISITED {
+            continue;
+        }
+        // each frame is a node and how many of its successors have been visited
+        let mut frames = vec![(start, 0)];
+        while let Some(&mut (node, ref mut visited)) = frames.last_mut() {
+            if *visited == 0 && index[node] == UNVISITED {
+                index[node] = next_index;
+                low_link[node] = next_index;
+                next_index += 1;
+                stack.push(node);
+                on_stack[node] = true;
+            }
+            if let Some(&next) = successors[node].get(*visited) {
+                *visited += 1;
+                if index[next] == UNVISITED {
+                    frames.push((next, 0));
+                } else if on_stack[next] {
+                    low_link[node] = low_link[node].min(index[next]);
+                }
+                continue;
+            }
+
+            frames.pop();
+            if let Some(&(parent, _)) = frames.last() {
+                low_link[parent] = low_link[parent].min(low_link[node]);
+            }
+            if low_link[node] == index[node] {
+                let mut component = vec![];
+                while let Some(member) = stack.pop() {
+                    on_stack[member] = false;
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                component.sort_unstable();
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::testing::build_graph;
+
+    #[test]
+    fn components_come_after_their_dependencies() {
+        // 0 -> 1 -> 2 -> 0 is a cycle, 3 depends on it, 4 on nothing, and 5 on itself
+        let successors = vec![vec![1], vec![2], vec![0], vec![0, 4], vec![], vec![5]];
+        let mut components = strongly_connected_components(&successors);
+        for component in &mut components {
+            component.sort_unstable();
+        }
+        let position = |node: usize| {
+            components
+                .iter()
+                .position(|component| component.contains(&node))
+                .unwrap()
+        };
+
+        assert_eq!(components.len(), 4);
+        assert!(components.contains(&vec![0, 1, 2]));
+        assert!(components.contains(&vec![5]));
+        assert!(position(0) < position(3));
+        assert!(position(4) < position(3));
+    }
+
+    #[test]
+    fn relative_imports() {
+        let module = PyModule::new("pkg.sub.m", Path::new("pkg/sub/m.py"));
+        let package = PyModule::new("pkg.sub", Path::new("pkg/sub/__init__.py"));
+        let resolve = resolve_relative_import;
+
+        assert_eq!(resolve(&module, "os", 0).as_deref(), Some("os"));
+        assert_eq!(resolve(&module, "x", 1).as_deref(), Some("pkg.sub.x"));
+        assert_eq!(resolve(&module, "", 1).as_deref(), Some("pkg.sub"));
+        assert_eq!(resolve(&module, "y.z", 2).as_deref(), Some("pkg.y.z"));
+        assert_eq!(resolve(&package, "x", 1).as_deref(), Some("pkg.sub.x"));
+        assert_eq!(resolve(&package, "x", 2).as_deref(), Some("pkg.x"));
+        assert_eq!(resolve(&module, "", 4), None);
+    }
+
+    #[test]
+    fn shortest_paths_come_first() {
+        // a reaches d through b and c before it reaches d directly
+        let graph = build_graph(&[(
+            "m.py",
+            "def a():\n    b()\n    d()\n\ndef b():\n    c()\n\ndef c():\n    d()\n\ndef d():\n    pass\n",
+        )]);
+        let a = graph.find("m::a").unwrap();
+        let d = graph.find("m::d").unwrap();
+        let names = |paths: Vec<Vec<usize>>| -> Vec<Vec<String>> {
+            paths
+                .iter()
+                .map(|path| {
+                    path.iter()
+                        .map(|&edge| graph.node(graph.edges()[edge].to).fqn.to_string())
+                        .collect()
+                })
+                .collect()
+        };
+
+        let paths = graph.simple_paths(a, d, FUNCTION_EDGES, 1);
+        assert_eq!(names(paths), [["m::d"]]);
+        let paths = graph.simple_paths(a, d, FUNCTION_EDGES, 5);
+        assert_eq!(names(paths), [vec!["m::d"], vec!["m::b", "m::c", "m::d"]]);
+        assert!(graph.simple_paths(d, a, FUNCTION_EDGES, 5).is_empty());
+    }
+}
@@ -0,0 +1,48 @@
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    fs,
+    io::{self, Write},
+};
+
+use serde::Serialize;
+
+use crate::{
+    graph::{DependencyGraph, Selection},
+    json::{json_graph, JsonGraph},
+};
+
+const TEMPLATE: &str = include_str!("report.html");
+
+#[derive(Serialize)]
+struct ReportData<'a> {
+    graph: JsonGraph<'a>,
+    /// Source of every file in the report, keyed by the paths used in the graph
+    sources: BTreeMap<String, String>,
+}
+
+/// Write a single page report that can be opened offline, with the graph and
+/// the source of every file in it embedded in the page
+pub fn write_html(
+    out: &mut impl Write,
+    graph: &DependencyGraph,
+    selection: &Selection,
+    root: Option<&str>,
+) -> io::Result<()> {
+    let mut sources = BTreeMap::new();
+    for &id in &selection.nodes {
+        if let Some(path) = &graph.node(id).path {
+            if let Entry::Vacant(entry) = sources.entry(path.to_string_lossy().to_string()) {
+                entry.insert(fs::read_to_string(path)?);
+            }
+        }
+    }
+
+    let data = ReportData {
+        graph: json_graph(graph, selection, root),
+        sources,
+    };
+    // a `<` can only appear inside json strings, and escaping it keeps things
+    // like `</script>` in the source from ending the script tag early
+    let data = serde_json::to_string(&data)?.replace('<', "\\u003c");
+    out.write_all(TEMPLATE.replace("/*PYDEP_DATA*/", &data).as_bytes())
+}
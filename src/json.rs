@@ -0,0 +1,247 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+use serde::Serialize;
+
+use crate::{
+    graph::{definitions, DependencyGraph, EdgeKind, Node, NodeKind, Selection},
+    project::{LoadOptions, Project},
+};
+
+/// Bumped whenever the shape of the JSON output changes in a way that could
+/// break consumers. Adding fields doesn't count
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+pub(crate) struct JsonGraph<'a> {
+    schema_version: u32,
+    /// The node a trace started from, or null for the whole project
+    root: Option<&'a str>,
+    nodes: Vec<JsonNode<'a>>,
+    edges: Vec<JsonEdge<'a>>,
+}
+
+// One line of NDJSON output
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum JsonRecord<'a> {
+    Header {
+        schema_version: u32,
+        root: Option<&'a str>,
+    },
+    Node(JsonNode<'a>),
+    Edge(JsonEdge<'a>),
+}
+
+#[derive(Serialize)]
+struct JsonNode<'a> {
+    id: &'a str,
+    kind: NodeKind,
+    module: &'a str,
+    external: bool,
+    path: Option<&'a Path>,
+    line: Option<usize>,
+    column: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct JsonEdge<'a> {
+    from: &'a str,
+    to: &'a str,
+    kind: EdgeKind,
+    path: Option<&'a Path>,
+    line: usize,
+    column: usize,
+}
+
+pub fn write_json(
+    out: &mut impl Write,
+    graph: &DependencyGraph,
+    selection: &Selection,
+    root: Option<&str>,
+) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut *out, &json_graph(graph, selection, root))?;
+    writeln!(out)
+}
+
+/// Write the header, then one line per node and edge, without building the
+/// whole document in memory first
+pub fn write_ndjson(
+    out: &mut impl Write,
+    graph: &DependencyGraph,
+    selection: &Selection,
+    root: Option<&str>,
+) -> io::Result<()> {
+    let header = JsonRecord::Header {
+        schema_version: SCHEMA_VERSION,
+        root,
+    };
+    let nodes = selection
+        .nodes
+        .iter()
+        .map(|&id| JsonRecord::Node(json_node(graph.node(id))));
+    let edges = selection
+        .edges
+        .iter()
+        .map(|&index| JsonRecord::Edge(json_edge(graph, index)));
+    for record in std::iter::once(header).chain(nodes).chain(edges) {
+        write_record(out, &record)?;
+    }
+    Ok(())
+}
+
+/// Load a project and write its whole graph as NDJSON along the way: the
+/// header first, the definitions in each file in module order as files get
+/// parsed, and the nodes outside the project and all edges once the graph is
+/// built
+pub fn stream_ndjson(
+    out: &mut (impl Write + Send),
+    path: &Path,
+    options: &LoadOptions,
+) -> io::Result<()> {
+    let header = JsonRecord::Header {
+        schema_version: SCHEMA_VERSION,
+        root: None,
+    };
+    write_record(out, &header)?;
+    let ordered = Mutex::new(InOrder {
+        out,
+        next: 0,
+        pending: BTreeMap::new(),
+    });
+    let project = Project::load_with(path, options, |index, module| {
+        let mut records = Vec::new();
+        for node in &definitions(module) {
+            write_record(&mut records, &JsonRecord::Node(json_node(node)))?;
+        }
+        ordered.lock().unwrap().write(index, records)
+    })?;
+
+    // everything in the project is a definition, and already written
+    let out = ordered.into_inner().unwrap().out;
+    let graph = &project.graph;
+    for node in graph.nodes().iter().filter(|node| node.external) {
+        write_record(out, &JsonRecord::Node(json_node(node)))?;
+    }
+    for index in 0..graph.edges().len() {
+        write_record(out, &JsonRecord::Edge(json_edge(graph, index)))?;
+    }
+    Ok(())
+}
+
+// Writes the records of each module in module order, however the files
+// finish parsing. A module's records wait until every module before it has
+// been written
+struct InOrder<W> {
+    out: W,
+    next: usize,
+    pending: BTreeMap<usize, Vec<u8>>,
+}
+
+impl<W: Write> InOrder<W> {
+    fn write(&mut self, index: usize, records: Vec<u8>) -> io::Result<()> {
+        self.pending.insert(index, records);
+        while let Some(records) = self.pending.remove(&self.next) {
+            self.out.write_all(&records)?;
+            self.next += 1;
+        }
+        Ok(())
+    }
+}
+
+fn write_record(out: &mut impl Write, record: &JsonRecord) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, record)?;
+    writeln!(out)
+}
+
+pub(crate) fn json_graph<'a>(
+    graph: &'a DependencyGraph,
+    selection: &Selection,
+    root: Option<&'a str>,
+) -> JsonGraph<'a> {
+    JsonGraph {
+        schema_version: SCHEMA_VERSION,
+        root,
+        nodes: selection
+            .nodes
+            .iter()
+            .map(|&id| json_node(graph.node(id)))
+            .collect(),
+        edges: selection
+            .edges
+            .iter()
+            .map(|&index| json_edge(graph, index))
+            .collect(),
+    }
+}
+
+fn json_node(node: &Node) -> JsonNode<'_> {
+    JsonNode {
+        id: &node.fqn,
+        kind: node.kind,
+        module: &node.module,
+        external: node.external,
+        path: node.path.as_deref(),
+        line: node.location.map(|location| location.line),
+        column: node.location.map(|location| location.column),
+    }
+}
+
+fn json_edge(graph: &DependencyGraph, index: usize) -> JsonEdge<'_> {
+    let edge = &graph.edges()[index];
+    JsonEdge {
+        from: &graph.node(edge.from).fqn,
+        to: &graph.node(edge.to).fqn,
+        kind: edge.kind,
+        path: graph.node(edge.from).path.as_deref(),
+        line: edge.location.line,
+        column: edge.location.column,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::testing::{build_graph, REPEATED_NAMES};
+
+    #[test]
+    fn node_ids_are_unique() {
+        let graph = build_graph(&[("pkg/m.py", REPEATED_NAMES)]);
+        let mut out = vec![];
+        write_json(&mut out, &graph, &graph.select_all(), None).unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        let ids: Vec<&str> = json["nodes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|node| node["id"].as_str().unwrap())
+            .collect();
+        let unique: HashSet<&str> = ids.iter().copied().collect();
+        assert_eq!(ids.len(), unique.len());
+        assert!(unique.contains("pkg.m::C.x"));
+        assert!(unique.contains("pkg.m::f"));
+    }
+
+    #[test]
+    fn modules_are_written_in_order() {
+        let mut out = vec![];
+        let mut ordered = InOrder {
+            out: &mut out,
+            next: 0,
+            pending: BTreeMap::new(),
+        };
+        ordered.write(2, b"c\n".to_vec()).unwrap();
+        ordered.write(1, b"b\n".to_vec()).unwrap();
+        assert!(ordered.out.is_empty());
+        ordered.write(0, b"a\n".to_vec()).unwrap();
+        ordered.write(3, b"d\n".to_vec()).unwrap();
+        assert_eq!(out, b"a\nb\nc\nd\n");
+    }
+}
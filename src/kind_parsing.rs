@@ -1,4 +1,11 @@
-use rustpython_parser::ast::{self, ExprKind, StmtKind};
+use std::{collections::BTreeSet, convert::Infallible};
+
+use rustpython_parser::{
+    ast::{self, fold, fold::Fold, ExprContext, ExprKind, StmtKind},
+    parser::parse_expression,
+};
+
+use crate::project::Reference;
 
 pub fn convert_attribute_to_name(node: &ExprKind) -> Option<String> {
     let mut name = String::new();
@@ -6,7 +13,7 @@ pub fn convert_attribute_to_name(node: &ExprKind) -> Option<String> {
     loop {
         match node {
             ExprKind::Attribute { value, attr, .. } => {
-                if name.len() > 0 {
+                if !name.is_empty() {
                     name = format!("{}.{}", attr, name);
                 } else {
                     name = attr.to_string();
@@ -22,72 +29,129 @@ pub fn convert_attribute_to_name(node: &ExprKind) -> Option<String> {
     }
 }
 
-pub fn find_calls_in_expr(node: &ExprKind) -> Vec<String> {
+// Name of a plain or dotted name expression like `foo` or `foo.bar.baz`
+pub fn expr_to_name(expr: &ast::Expr) -> Option<String> {
+    match &expr.node {
+        ExprKind::Attribute { .. } => convert_attribute_to_name(&expr.node),
+        ExprKind::Name { id, ctx: _ } => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+// The name a decorator refers to, either used bare like `@cache` or called
+// like `@app.route("/")`
+pub fn find_decorator(expr: &ast::Expr) -> Option<Reference> {
+    match &expr.node {
+        ExprKind::Call { func, .. } => expr_to_name(func),
+        _ => expr_to_name(expr),
+    }
+    .map(|name| Reference::new(&name, expr.location))
+}
+
+// Names used in a type annotation, including inside generics like
+// `List[Foo]`, unions like `Foo | Bar`, and forward references like `"Foo"`
+pub fn find_names_in_annotation(expr: &ast::Expr) -> Vec<Reference> {
+    let mut names = Vec::new();
+    match &expr.node {
+        ExprKind::Name { .. } | ExprKind::Attribute { .. } => {
+            if let Some(name) = expr_to_name(expr) {
+                names.push(Reference::new(&name, expr.location));
+            }
+        }
+        ExprKind::Subscript { value, slice, .. } => {
+            names.append(&mut find_names_in_annotation(value));
+            names.append(&mut find_names_in_annotation(slice));
+        }
+        ExprKind::BinOp { left, right, .. } => {
+            names.append(&mut find_names_in_annotation(left));
+            names.append(&mut find_names_in_annotation(right));
+        }
+        ExprKind::Tuple { elts, .. } | ExprKind::List { elts, .. } => {
+            for elt in elts {
+                names.append(&mut find_names_in_annotation(elt));
+            }
+        }
+        ExprKind::Constant {
+            value: ast::Constant::Str(forward_ref),
+            ..
+        } if !forward_ref.is_empty()
+            && forward_ref
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '.') =>
+        {
+            names.push(Reference::new(forward_ref, expr.location));
+        }
+        _ => {}
+    }
+    names
+}
+
+pub fn find_calls_in_expr(expr: &ast::Expr, di_patterns: &[String]) -> Vec<Reference> {
     let mut calls = Vec::new();
-    match node {
+    match &expr.node {
         ExprKind::BoolOp { op: _, values } => {
             for value in values {
-                calls.append(&mut find_calls_in_expr(&value.node));
+                calls.append(&mut find_calls_in_expr(value, di_patterns));
             }
         }
         ExprKind::NamedExpr { target, value } => {
-            calls.append(&mut find_calls_in_expr(&target.node));
-            calls.append(&mut find_calls_in_expr(&value.node));
+            calls.append(&mut find_calls_in_expr(target, di_patterns));
+            calls.append(&mut find_calls_in_expr(value, di_patterns));
         }
         ExprKind::BinOp { left, op: _, right } => {
-            calls.append(&mut find_calls_in_expr(&left.node));
-            calls.append(&mut find_calls_in_expr(&right.node));
+            calls.append(&mut find_calls_in_expr(left, di_patterns));
+            calls.append(&mut find_calls_in_expr(right, di_patterns));
         }
         ExprKind::UnaryOp { op: _, operand } => {
-            calls.append(&mut find_calls_in_expr(&operand.node));
+            calls.append(&mut find_calls_in_expr(operand, di_patterns));
         }
         ExprKind::Lambda { args: _, body } => {
-            calls.append(&mut find_calls_in_expr(&body.node));
+            calls.append(&mut find_calls_in_expr(body, di_patterns));
         }
         ExprKind::IfExp { test, body, orelse } => {
-            calls.append(&mut find_calls_in_expr(&test.node));
-            calls.append(&mut find_calls_in_expr(&body.node));
-            calls.append(&mut find_calls_in_expr(&orelse.node));
+            calls.append(&mut find_calls_in_expr(test, di_patterns));
+            calls.append(&mut find_calls_in_expr(body, di_patterns));
+            calls.append(&mut find_calls_in_expr(orelse, di_patterns));
         }
         ExprKind::Dict { keys, values } => {
             for key in keys {
-                calls.append(&mut find_calls_in_expr(&key.node));
+                calls.append(&mut find_calls_in_expr(key, di_patterns));
             }
             for value in values {
-                calls.append(&mut find_calls_in_expr(&value.node));
+                calls.append(&mut find_calls_in_expr(value, di_patterns));
             }
         }
         ExprKind::Slice { lower, upper, step } => {
             if let Some(lower) = lower {
-                calls.append(&mut find_calls_in_expr(&lower.node));
+                calls.append(&mut find_calls_in_expr(lower, di_patterns));
             }
             if let Some(upper) = upper {
-                calls.append(&mut find_calls_in_expr(&upper.node));
+                calls.append(&mut find_calls_in_expr(upper, di_patterns));
             }
             if let Some(step) = step {
-                calls.append(&mut find_calls_in_expr(&step.node));
+                calls.append(&mut find_calls_in_expr(step, di_patterns));
             }
         }
         ExprKind::Set { elts } => {
             for elt in elts {
-                calls.append(&mut find_calls_in_expr(&elt.node));
+                calls.append(&mut find_calls_in_expr(elt, di_patterns));
             }
         }
         ExprKind::ListComp { elt, generators } => {
-            calls.append(&mut find_calls_in_expr(&elt.node));
+            calls.append(&mut find_calls_in_expr(elt, di_patterns));
             for generator in generators {
-                calls.append(&mut find_calls_in_expr(&generator.iter.node));
+                calls.append(&mut find_calls_in_expr(&generator.iter, di_patterns));
                 for if_expr in &generator.ifs {
-                    calls.append(&mut find_calls_in_expr(&if_expr.node));
+                    calls.append(&mut find_calls_in_expr(if_expr, di_patterns));
                 }
             }
         }
         ExprKind::SetComp { elt, generators } => {
-            calls.append(&mut find_calls_in_expr(&elt.node));
+            calls.append(&mut find_calls_in_expr(elt, di_patterns));
             for generator in generators {
-                calls.append(&mut find_calls_in_expr(&generator.iter.node));
+                calls.append(&mut find_calls_in_expr(&generator.iter, di_patterns));
                 for if_expr in &generator.ifs {
-                    calls.append(&mut find_calls_in_expr(&if_expr.node));
+                    calls.append(&mut find_calls_in_expr(if_expr, di_patterns));
                 }
             }
         }
@@ -96,62 +160,70 @@ pub fn find_calls_in_expr(node: &ExprKind) -> Vec<String> {
             value,
             generators,
         } => {
-            calls.append(&mut find_calls_in_expr(&key.node));
-            calls.append(&mut find_calls_in_expr(&value.node));
+            calls.append(&mut find_calls_in_expr(key, di_patterns));
+            calls.append(&mut find_calls_in_expr(value, di_patterns));
             for generator in generators {
-                calls.append(&mut find_calls_in_expr(&generator.iter.node));
+                calls.append(&mut find_calls_in_expr(&generator.iter, di_patterns));
                 for if_expr in &generator.ifs {
-                    calls.append(&mut find_calls_in_expr(&if_expr.node));
+                    calls.append(&mut find_calls_in_expr(if_expr, di_patterns));
                 }
             }
         }
         ExprKind::GeneratorExp { elt, generators } => {
-            calls.append(&mut find_calls_in_expr(&elt.node));
+            calls.append(&mut find_calls_in_expr(elt, di_patterns));
             for generator in generators {
-                calls.append(&mut find_calls_in_expr(&generator.iter.node));
+                calls.append(&mut find_calls_in_expr(&generator.iter, di_patterns));
                 for if_expr in &generator.ifs {
-                    calls.append(&mut find_calls_in_expr(&if_expr.node));
+                    calls.append(&mut find_calls_in_expr(if_expr, di_patterns));
                 }
             }
         }
         ExprKind::Await { value } => {
-            calls.append(&mut find_calls_in_expr(&value.node));
+            calls.append(&mut find_calls_in_expr(value, di_patterns));
         }
         ExprKind::Yield { value } => {
             if let Some(value) = value {
-                calls.append(&mut find_calls_in_expr(&value.node));
+                calls.append(&mut find_calls_in_expr(value, di_patterns));
             }
         }
         ExprKind::YieldFrom { value } => {
-            calls.append(&mut find_calls_in_expr(&value.node));
+            calls.append(&mut find_calls_in_expr(value, di_patterns));
         }
         ExprKind::Compare {
             left,
             ops: _,
             comparators,
         } => {
-            calls.append(&mut find_calls_in_expr(&left.node));
+            calls.append(&mut find_calls_in_expr(left, di_patterns));
             for comparator in comparators {
-                calls.append(&mut find_calls_in_expr(&comparator.node));
+                calls.append(&mut find_calls_in_expr(comparator, di_patterns));
             }
         }
         ExprKind::Call {
             func,
             args,
-            keywords: _,
+            keywords,
         } => {
-            println!("func: {:#?}", func.node);
-            if let Some(name) = match &func.node {
-                ExprKind::Attribute { .. } => convert_attribute_to_name(&func.node),
-                ExprKind::Name { id, ctx: _ } => Some(id.to_string()),
-                _ => None,
-            } {
-                calls.push(name);
+            if let Some(name) = expr_to_name(func) {
+                // a dependency injection lookup depends on the class it names
+                if di_patterns.contains(&name) {
+                    if let Some(ExprKind::Constant {
+                        value: ast::Constant::Str(class_name),
+                        ..
+                    }) = args.first().map(|arg| &arg.node)
+                    {
+                        calls.push(Reference::lookup(class_name, expr.location));
+                    }
+                }
+                calls.push(Reference::new(&name, expr.location));
             } else {
-                calls.append(&mut find_calls_in_expr(&func.node));
+                calls.append(&mut find_calls_in_expr(func, di_patterns));
             }
             for arg in args {
-                calls.append(&mut find_calls_in_expr(&arg.node));
+                calls.append(&mut find_calls_in_expr(arg, di_patterns));
+            }
+            for keyword in keywords {
+                calls.append(&mut find_calls_in_expr(&keyword.node.value, di_patterns));
             }
         }
         ExprKind::FormattedValue {
@@ -159,14 +231,14 @@ pub fn find_calls_in_expr(node: &ExprKind) -> Vec<String> {
             conversion: _,
             format_spec,
         } => {
-            calls.append(&mut find_calls_in_expr(&value.node));
+            calls.append(&mut find_calls_in_expr(value, di_patterns));
             if let Some(format_spec) = format_spec {
-                calls.append(&mut find_calls_in_expr(&format_spec.node));
+                calls.append(&mut find_calls_in_expr(format_spec, di_patterns));
             }
         }
         ExprKind::JoinedStr { values } => {
             for value in values {
-                calls.append(&mut find_calls_in_expr(&value.node));
+                calls.append(&mut find_calls_in_expr(value, di_patterns));
             }
         }
         ExprKind::Constant { value: _, kind: _ } => {}
@@ -175,46 +247,46 @@ pub fn find_calls_in_expr(node: &ExprKind) -> Vec<String> {
             attr: _,
             ctx: _,
         } => {
-            calls.append(&mut find_calls_in_expr(&value.node));
+            calls.append(&mut find_calls_in_expr(value, di_patterns));
         }
         ExprKind::Subscript {
             value,
             slice,
             ctx: _,
         } => {
-            calls.append(&mut find_calls_in_expr(&value.node));
-            calls.append(&mut find_calls_in_expr(&slice.node));
+            calls.append(&mut find_calls_in_expr(value, di_patterns));
+            calls.append(&mut find_calls_in_expr(slice, di_patterns));
         }
         ExprKind::Starred { value, ctx: _ } => {
-            calls.append(&mut find_calls_in_expr(&value.node));
+            calls.append(&mut find_calls_in_expr(value, di_patterns));
         }
         ExprKind::Name { id: _, ctx: _ } => {}
         ExprKind::List { elts, ctx: _ } => {
             for elt in elts {
-                calls.append(&mut find_calls_in_expr(&elt.node));
+                calls.append(&mut find_calls_in_expr(elt, di_patterns));
             }
         }
         ExprKind::Tuple { elts, ctx: _ } => {
             for elt in elts {
-                calls.append(&mut find_calls_in_expr(&elt.node));
+                calls.append(&mut find_calls_in_expr(elt, di_patterns));
             }
         }
     }
     calls
 }
 
-pub fn find_calls_in_stmt(node: &StmtKind) -> Vec<String> {
+pub fn find_calls_in_stmt(stmt: &ast::Stmt, di_patterns: &[String]) -> Vec<Reference> {
     let mut calls = Vec::new();
-    match node {
+    match &stmt.node {
         StmtKind::Match { subject, cases } => {
-            calls.append(&mut find_calls_in_expr(&subject.node));
+            calls.append(&mut find_calls_in_expr(subject, di_patterns));
             for case in cases {
-                for guard in &case.guard {
-                    calls.append(&mut find_calls_in_expr(&guard.node));
+                if let Some(guard) = &case.guard {
+                    calls.append(&mut find_calls_in_expr(guard, di_patterns));
                 }
                 // skipping patterns for now
                 for stmt in &case.body {
-                    calls.append(&mut find_calls_in_stmt(&stmt.node));
+                    calls.append(&mut find_calls_in_stmt(stmt, di_patterns));
                 }
             }
         }
@@ -225,13 +297,13 @@ pub fn find_calls_in_stmt(node: &StmtKind) -> Vec<String> {
             orelse,
             type_comment: _,
         } => {
-            calls.append(&mut find_calls_in_expr(&target.node));
-            calls.append(&mut find_calls_in_expr(&iter.node));
+            calls.append(&mut find_calls_in_expr(target, di_patterns));
+            calls.append(&mut find_calls_in_expr(iter, di_patterns));
             for stmt in body {
-                calls.append(&mut find_calls_in_stmt(&stmt.node));
+                calls.append(&mut find_calls_in_stmt(stmt, di_patterns));
             }
             for stmt in orelse {
-                calls.append(&mut find_calls_in_stmt(&stmt.node));
+                calls.append(&mut find_calls_in_stmt(stmt, di_patterns));
             }
         }
         StmtKind::AsyncFunctionDef {
@@ -243,10 +315,10 @@ pub fn find_calls_in_stmt(node: &StmtKind) -> Vec<String> {
             type_comment: _,
         } => {
             for decorator in decorator_list {
-                calls.append(&mut find_calls_in_expr(&decorator.node));
+                calls.append(&mut find_calls_in_expr(decorator, di_patterns));
             }
             for stmt in body {
-                calls.append(&mut find_calls_in_stmt(&stmt.node));
+                calls.append(&mut find_calls_in_stmt(stmt, di_patterns));
             }
         }
         StmtKind::AsyncWith {
@@ -255,10 +327,10 @@ pub fn find_calls_in_stmt(node: &StmtKind) -> Vec<String> {
             type_comment: _,
         } => {
             for item in items {
-                calls.append(&mut find_calls_in_expr(&item.context_expr.node));
+                calls.append(&mut find_calls_in_expr(&item.context_expr, di_patterns));
             }
             for stmt in body {
-                calls.append(&mut find_calls_in_stmt(&stmt.node));
+                calls.append(&mut find_calls_in_stmt(stmt, di_patterns));
             }
         }
         StmtKind::AnnAssign {
@@ -268,25 +340,25 @@ pub fn find_calls_in_stmt(node: &StmtKind) -> Vec<String> {
             simple: _,
         } => {
             if let Some(value) = value {
-                calls.append(&mut find_calls_in_expr(&value.node));
+                calls.append(&mut find_calls_in_expr(value, di_patterns));
             }
         }
         StmtKind::Assert { test, msg: _ } => {
-            calls.append(&mut find_calls_in_expr(&test.node));
+            calls.append(&mut find_calls_in_expr(test, di_patterns));
         }
         StmtKind::Assign {
             targets: _,
             value,
             type_comment: _,
         } => {
-            calls.append(&mut find_calls_in_expr(&value.node));
+            calls.append(&mut find_calls_in_expr(value, di_patterns));
         }
         StmtKind::AugAssign {
             target: _,
             op: _,
             value,
         } => {
-            calls.append(&mut find_calls_in_expr(&value.node));
+            calls.append(&mut find_calls_in_expr(value, di_patterns));
         }
         StmtKind::Break => {}
         StmtKind::ClassDef {
@@ -297,20 +369,20 @@ pub fn find_calls_in_stmt(node: &StmtKind) -> Vec<String> {
             decorator_list,
         } => {
             for decorator in decorator_list {
-                calls.append(&mut find_calls_in_expr(&decorator.node));
+                calls.append(&mut find_calls_in_expr(decorator, di_patterns));
             }
             for stmt in body {
-                calls.append(&mut find_calls_in_stmt(&stmt.node));
+                calls.append(&mut find_calls_in_stmt(stmt, di_patterns));
             }
         }
         StmtKind::Continue => {}
         StmtKind::Delete { targets } => {
             for target in targets {
-                calls.append(&mut find_calls_in_expr(&target.node));
+                calls.append(&mut find_calls_in_expr(target, di_patterns));
             }
         }
         StmtKind::Expr { value } => {
-            calls.append(&mut find_calls_in_expr(&value.node));
+            calls.append(&mut find_calls_in_expr(value, di_patterns));
         }
         StmtKind::For {
             target,
@@ -319,13 +391,13 @@ pub fn find_calls_in_stmt(node: &StmtKind) -> Vec<String> {
             orelse,
             type_comment: _,
         } => {
-            calls.append(&mut find_calls_in_expr(&target.node));
-            calls.append(&mut find_calls_in_expr(&iter.node));
+            calls.append(&mut find_calls_in_expr(target, di_patterns));
+            calls.append(&mut find_calls_in_expr(iter, di_patterns));
             for stmt in body {
-                calls.append(&mut find_calls_in_stmt(&stmt.node));
+                calls.append(&mut find_calls_in_stmt(stmt, di_patterns));
             }
             for stmt in orelse {
-                calls.append(&mut find_calls_in_stmt(&stmt.node));
+                calls.append(&mut find_calls_in_stmt(stmt, di_patterns));
             }
         }
         StmtKind::FunctionDef {
@@ -337,20 +409,20 @@ pub fn find_calls_in_stmt(node: &StmtKind) -> Vec<String> {
             type_comment: _,
         } => {
             for decorator in decorator_list {
-                calls.append(&mut find_calls_in_expr(&decorator.node));
+                calls.append(&mut find_calls_in_expr(decorator, di_patterns));
             }
             for stmt in body {
-                calls.append(&mut find_calls_in_stmt(&stmt.node));
+                calls.append(&mut find_calls_in_stmt(stmt, di_patterns));
             }
         }
         StmtKind::Global { names: _ } => {}
         StmtKind::If { test, body, orelse } => {
-            calls.append(&mut find_calls_in_expr(&test.node));
+            calls.append(&mut find_calls_in_expr(test, di_patterns));
             for stmt in body {
-                calls.append(&mut find_calls_in_stmt(&stmt.node));
+                calls.append(&mut find_calls_in_stmt(stmt, di_patterns));
             }
             for stmt in orelse {
-                calls.append(&mut find_calls_in_stmt(&stmt.node));
+                calls.append(&mut find_calls_in_stmt(stmt, di_patterns));
             }
         }
         StmtKind::Import { names: _ } => {}
@@ -363,15 +435,15 @@ pub fn find_calls_in_stmt(node: &StmtKind) -> Vec<String> {
         StmtKind::Pass => {}
         StmtKind::Raise { exc, cause } => {
             if let Some(exc) = exc {
-                calls.append(&mut find_calls_in_expr(&exc.node));
+                calls.append(&mut find_calls_in_expr(exc, di_patterns));
             }
             if let Some(cause) = cause {
-                calls.append(&mut find_calls_in_expr(&cause.node));
+                calls.append(&mut find_calls_in_expr(cause, di_patterns));
             }
         }
         StmtKind::Return { value } => {
             if let Some(value) = value {
-                calls.append(&mut find_calls_in_expr(&value.node));
+                calls.append(&mut find_calls_in_expr(value, di_patterns));
             }
         }
         StmtKind::Try {
@@ -381,19 +453,19 @@ pub fn find_calls_in_stmt(node: &StmtKind) -> Vec<String> {
             finalbody,
         } => {
             for stmt in body {
-                calls.append(&mut find_calls_in_stmt(&stmt.node));
+                calls.append(&mut find_calls_in_stmt(stmt, di_patterns));
             }
             for handler in handlers {
                 let ast::ExcepthandlerKind::ExceptHandler { body, .. } = &handler.node;
                 for stmt in body {
-                    calls.append(&mut find_calls_in_stmt(&stmt.node));
+                    calls.append(&mut find_calls_in_stmt(stmt, di_patterns));
                 }
             }
             for stmt in orelse {
-                calls.append(&mut find_calls_in_stmt(&stmt.node));
+                calls.append(&mut find_calls_in_stmt(stmt, di_patterns));
             }
             for stmt in finalbody {
-                calls.append(&mut find_calls_in_stmt(&stmt.node));
+                calls.append(&mut find_calls_in_stmt(stmt, di_patterns));
             }
         }
         StmtKind::While {
@@ -402,10 +474,10 @@ pub fn find_calls_in_stmt(node: &StmtKind) -> Vec<String> {
             orelse,
         } => {
             for stmt in body {
-                calls.append(&mut find_calls_in_stmt(&stmt.node));
+                calls.append(&mut find_calls_in_stmt(stmt, di_patterns));
             }
             for stmt in orelse {
-                calls.append(&mut find_calls_in_stmt(&stmt.node));
+                calls.append(&mut find_calls_in_stmt(stmt, di_patterns));
             }
         }
         StmtKind::With {
@@ -414,12 +486,122 @@ pub fn find_calls_in_stmt(node: &StmtKind) -> Vec<String> {
             type_comment: _,
         } => {
             for item in items {
-                calls.append(&mut find_calls_in_expr(&item.context_expr.node));
+                calls.append(&mut find_calls_in_expr(&item.context_expr, di_patterns));
             }
             for stmt in body {
-                calls.append(&mut find_calls_in_stmt(&stmt.node));
+                calls.append(&mut find_calls_in_stmt(stmt, di_patterns));
             }
         }
     }
     calls
 }
+
+// Every name read anywhere in the statements it's folded over, including
+// inside string annotations like `"Foo"` or `"list[Foo]"`
+#[derive(Default)]
+pub struct NameCollector {
+    pub names: BTreeSet<String>,
+}
+
+impl NameCollector {
+    fn add_annotation(&mut self, expr: &ast::Expr) {
+        match &expr.node {
+            ExprKind::Constant {
+                value: ast::Constant::Str(source),
+                ..
+            } => {
+                if let Ok(parsed) = parse_expression(source, "<annotation>") {
+                    self.add_annotation(&parsed);
+                    let _ = self.fold_expr(parsed);
+                }
+            }
+            ExprKind::Subscript { value, slice, .. } => {
+                self.add_annotation(value);
+                self.add_annotation(slice);
+            }
+            ExprKind::BinOp { left, right, .. } => {
+                self.add_annotation(left);
+                self.add_annotation(right);
+            }
+            ExprKind::Tuple { elts, .. } | ExprKind::List { elts, .. } => {
+                for elt in elts {
+                    self.add_annotation(elt);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Fold<()> for NameCollector {
+    type TargetU = ();
+    type Error = Infallible;
+
+    fn map_user(&mut self, user: ()) -> Result<(), Infallible> {
+        Ok(user)
+    }
+
+    fn fold_stmt(&mut self, stmt: ast::Stmt) -> Result<ast::Stmt, Infallible> {
+        match &stmt.node {
+            StmtKind::FunctionDef {
+                returns: Some(returns),
+                ..
+            }
+            | StmtKind::AsyncFunctionDef {
+                returns: Some(returns),
+                ..
+            } => self.add_annotation(returns),
+            StmtKind::AnnAssign { annotation, .. } => self.add_annotation(annotation),
+            _ => {}
+        }
+        fold::fold_stmt(self, stmt)
+    }
+
+    fn fold_arg(&mut self, arg: ast::Arg) -> Result<ast::Arg, Infallible> {
+        if let Some(annotation) = &arg.node.annotation {
+            self.add_annotation(annotation);
+        }
+        fold::fold_arg(self, arg)
+    }
+
+    fn fold_expr(&mut self, expr: ast::Expr) -> Result<ast::Expr, Infallible> {
+        if let ExprKind::Name { id, ctx } = &expr.node {
+            if *ctx != ExprContext::Store {
+                self.names.insert(id.to_string());
+            }
+        }
+        fold::fold_expr(self, expr)
+    }
+}
+
+// The names a statement like `__all__ = ["a", "b"]` or `__all__ += ("c",)`
+// adds to `__all__`
+pub fn find_all_names(stmt: &ast::Stmt) -> Option<Vec<String>> {
+    let (target, value) = match &stmt.node {
+        StmtKind::Assign { targets, value, .. } if targets.len() == 1 => (&targets[0], value),
+        StmtKind::AugAssign { target, value, .. } => (target.as_ref(), value),
+        StmtKind::AnnAssign {
+            target,
+            value: Some(value),
+            ..
+        } => (target.as_ref(), value),
+        _ => return None,
+    };
+    if !matches!(&target.node, ExprKind::Name { id, .. } if id == "__all__") {
+        return None;
+    }
+    let (ExprKind::List { elts, .. } | ExprKind::Tuple { elts, .. }) = &value.node else {
+        return None;
+    };
+    Some(
+        elts.iter()
+            .filter_map(|elt| match &elt.node {
+                ExprKind::Constant {
+                    value: ast::Constant::Str(name),
+                    ..
+                } => Some(name.to_string()),
+                _ => None,
+            })
+            .collect(),
+    )
+}
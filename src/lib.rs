@@ -0,0 +1,31 @@
+//! Dependency tracing for python projects.
+//!
+//! Load a project with [`project::Project::load`], then query it for the
+//! transitive dependencies of its modules and functions.
+
+pub mod cache;
+pub mod config;
+pub mod contracts;
+pub mod cycles;
+pub mod deadcode;
+pub mod dot;
+pub mod dsm;
+pub mod graph;
+pub mod html;
+pub mod json;
+mod kind_parsing;
+pub mod mermaid;
+pub mod metrics;
+pub mod network;
+pub mod paths;
+pub mod project;
+pub mod roots;
+pub mod sqlite;
+pub mod summary;
+pub mod table;
+#[cfg(test)]
+mod testing;
+pub mod tree;
+pub mod unresolved;
+pub mod unused_imports;
+pub mod usage;
@@ -1,228 +1,633 @@
-use clap::Parser;
-use kind_parsing::find_calls_in_stmt;
-use rustpython_parser::{ast, parser::parse_program};
-use std::collections::HashMap;
-use std::fs;
+use clap::{ArgGroup, Parser, Subcommand};
+use pydep::{
+    config::{find_config, load_config, Config, Format},
+    contracts::{check_contracts, write_violations, Contract},
+    cycles::{find_import_cycles, write_cycles, CycleBaseline},
+    deadcode::{
+        find_dead_code, find_entry_points, find_unparsed_test_dirs, write_dead_code, Confidence,
+        Whitelist,
+    },
+    dot::write_dot,
+    dsm::Dsm,
+    graph::{NodeKind, FUNCTION_EDGES, MODULE_EDGES},
+    html::write_html,
+    json::{stream_ndjson, write_json, write_ndjson},
+    mermaid::write_mermaid,
+    metrics::{MetricsSort, ProjectMetrics},
+    network::{write_gexf, write_graphml, NetworkView},
+    paths::write_paths,
+    project::{not_found, LoadOptions, Project},
+    sqlite::export_sqlite,
+    summary::write_project_summary,
+    tree::{write_transitive_deps, TreeOptions},
+    unresolved::write_unresolved,
+    unused_imports::{find_unused_imports, remove_unused_imports, write_unused_imports},
+    usage::write_usage_table,
+};
+use std::collections::HashSet;
 use std::fs::File;
-use std::io::{self, Read};
+use std::io::{self, BufWriter, Write};
 use std::path::{Path, PathBuf};
-use tree::print_transitive_deps;
-
-#[derive(Debug)]
-pub struct PyModule {
-    name: String,
-    path: PathBuf,
-    imports: Vec<String>,
-    import_froms: Vec<(String, Vec<String>)>,
-    functions: Vec<(String, Vec<String>)>,
-    classes: Vec<PyClass>,
+use std::process;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// The project to analyze
+    #[arg(short, long, global = true, default_value = ".")]
+    project: PathBuf,
+
+    /// Source root to import modules from, relative to the project. May be
+    /// repeated, and defaults to the package dirs in pyproject.toml
+    #[arg(short, long, global = true)]
+    root: Vec<PathBuf>,
+
+    /// Glob pattern for files and directories to skip, relative to the
+    /// project. May be repeated
+    #[arg(short, long, global = true)]
+    exclude: Vec<String>,
+
+    /// Config file to use instead of searching for pydep.toml or pyproject.toml
+    #[arg(short, long, global = true)]
+    config: Option<PathBuf>,
+
+    /// The module to analyze. Leave it out to describe the whole project,
+    /// except in mermaid output
+    #[arg(short, long)]
+    module: Option<String>,
+
+    /// Optional function to analyze
+    #[arg(short, long, requires = "module")]
+    function: Option<String>,
+
+    /// Output format
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+
+    /// How many levels below the root to show in text and mermaid output
+    #[arg(long)]
+    max_depth: Option<usize>,
+
+    /// Instead of dependencies, list the calls made by --module, --function
+    /// and what it calls, or the whole project, that couldn't be resolved to
+    /// project code, and why
+    #[arg(long)]
+    show_unresolved: bool,
+
+    /// Show a node's dependencies only the first time it appears in text
+    /// output, and print it as `name (see above)` after that
+    #[arg(long)]
+    elide_repeats: bool,
+
+    /// Parse every file again instead of reusing results from .pydep_cache
+    #[arg(long, global = true)]
+    no_cache: bool,
+
+    #[arg(long, global = true)]
+    debug: bool,
 }
 
-#[derive(Debug)]
-pub struct PyClass {
-    name: String,
-    methods: Vec<(String, Vec<String>)>,
+#[derive(Subcommand)]
+enum Command {
+    /// Write the whole dependency graph out for other tools
+    #[command(group(ArgGroup::new("target").required(true).multiple(true)))]
+    Export {
+        /// Write a SQLite database of files, modules, classes, functions,
+        /// imports, and calls
+        #[arg(long, group = "target")]
+        sqlite: Option<PathBuf>,
+
+        /// Write a GraphML file, for yEd, Gephi, or networkx
+        #[arg(long, group = "target")]
+        graphml: Option<PathBuf>,
+
+        /// Write a GEXF file, for Gephi
+        #[arg(long, group = "target")]
+        gexf: Option<PathBuf>,
+
+        /// Which graph to write as GraphML or GEXF
+        #[arg(long, value_enum, default_value_t)]
+        graph: NetworkView,
+    },
+
+    /// Find groups of modules that import each other. Exits with an error
+    /// if there are cycles through module level imports that the baseline
+    /// doesn't list
+    Cycles {
+        /// File of known cycles, which are still shown but not treated as errors
+        #[arg(long)]
+        baseline: Option<PathBuf>,
+
+        /// Write the cycles found to the baseline file instead of checking
+        /// against it
+        #[arg(long, requires = "baseline")]
+        update_baseline: bool,
+    },
+
+    /// Show how one module, function, or method comes to depend on another.
+    /// Modules are connected by imports, and functions and methods by calls
+    Path {
+        /// Fully qualified name to start from, like `package.module` or
+        /// `package.module::function`
+        #[arg(long)]
+        from: String,
+
+        /// Fully qualified name of the dependency to find
+        #[arg(long)]
+        to: String,
+
+        /// Show every path that doesn't visit a node twice, instead of only
+        /// the shortest
+        #[arg(long)]
+        all: bool,
+
+        /// The most paths to show with --all
+        #[arg(long, default_value_t = 20, requires = "all")]
+        limit: usize,
+    },
+
+    /// Find functions, methods, and classes that nothing reachable from an
+    /// entry point uses. Entry points are modules with a __main__ block,
+    /// console scripts, test files, functions registered by framework
+    /// decorators, and the entry-points in the config
+    DeadCode {
+        /// Another fully qualified name to count as an entry point, like
+        /// `package.cli::main`. May be repeated
+        #[arg(long)]
+        entry_point: Vec<String>,
+
+        /// File of glob patterns for unused code to leave out, instead of
+        /// the config's dead-code-whitelist
+        #[arg(long)]
+        whitelist: Option<PathBuf>,
+
+        /// Only show findings at least this sure
+        #[arg(long, value_enum, default_value = "low")]
+        min_confidence: Confidence,
+    },
+
+    /// Check the contracts in the config about which modules may import
+    /// which. Exits with an error if any are broken
+    Check {
+        /// Also count imports made through other modules, for every contract
+        #[arg(long)]
+        transitive: bool,
+    },
+
+    /// Find imports outside functions and classes that their module never uses. Exits with an
+    /// error if there are any left
+    UnusedImports {
+        /// Remove the unused imports from the source files
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// Summarize the whole project
+    Report {
+        #[command(subcommand)]
+        report: Report,
+    },
 }
 
-impl PyModule {
-    fn new(name: &str, path: &Path) -> PyModule {
-        PyModule {
-            name: name.to_string(),
-            path: path.to_path_buf(),
-            imports: vec![],
-            import_froms: vec![],
-            functions: vec![],
-            classes: vec![],
+#[derive(Subcommand)]
+enum Report {
+    /// How many times each module is imported and each function and class is
+    /// used, and by what
+    Table {
+        /// Write CSV instead of aligned columns
+        #[arg(long)]
+        csv: bool,
+    },
+    /// A package by package matrix of imports, where rows depend on columns
+    Dsm {
+        /// Write CSV instead of aligned columns
+        #[arg(long)]
+        csv: bool,
+    },
+    /// Coupling, instability, abstractness, and distance from the main
+    /// sequence, for every module and package
+    Metrics {
+        /// How many parts of a module name make up its package
+        #[arg(long, default_value_t = 1)]
+        depth: usize,
+
+        /// The column to order rows by
+        #[arg(long, value_enum, default_value_t)]
+        sort: MetricsSort,
+
+        /// Write CSV instead of aligned columns
+        #[arg(long, conflicts_with = "json")]
+        csv: bool,
+
+        /// Write JSON instead of aligned columns
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+// Settings after applying command line overrides to the config file
+struct Settings {
+    load_options: LoadOptions,
+    format: Format,
+    entry_points: Vec<String>,
+    dead_code_whitelist: Option<PathBuf>,
+    contracts: Vec<Contract>,
+}
+
+impl Settings {
+    fn new(config: Config, args: &Args) -> Settings {
+        let roots = if args.root.is_empty() {
+            config.roots
+        } else {
+            args.root.clone()
+        };
+        let mut exclude = config.exclude;
+        exclude.extend(args.exclude.iter().cloned());
+        Settings {
+            load_options: LoadOptions {
+                roots,
+                exclude,
+                di_patterns: config.di_patterns,
+                cache: !args.no_cache,
+            },
+            format: args.format.or(config.format).unwrap_or_default(),
+            entry_points: config.entry_points,
+            dead_code_whitelist: config.dead_code_whitelist,
+            contracts: config.contracts,
         }
     }
 }
 
-mod kind_parsing;
-mod tree;
+fn main() {
+    if let Err(e) = run(Args::parse()) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
 
-fn parse_module(name: &str, source_code: &str, path: &Path) -> PyModule {
-    let mut parsed_module = PyModule::new(name, path);
+fn run(args: Args) -> io::Result<()> {
+    let config = match &args.config {
+        Some(path) => load_config(path)?,
+        None => find_config(&args.project)?.unwrap_or_default(),
+    };
+    let settings = Settings::new(config, &args);
+    // the whole project in NDJSON is written while it loads
+    let streams = settings.format == Format::Ndjson && args.module.is_none();
+    if args.command.is_none() && streams && !args.show_unresolved && !args.debug {
+        let out = &mut BufWriter::new(io::stdout());
+        stream_ndjson(out, &args.project, &settings.load_options)?;
+        return out.flush();
+    }
+    let project = Project::load(&args.project, &settings.load_options)?;
+    if args.debug {
+        println!("{:#?}", project.modules);
+    }
 
-    let ast = parse_program(source_code, path.to_str().unwrap()).unwrap();
-    for located in ast {
-        let node = located.node;
-        match &node {
-            ast::StmtKind::Import { names } => {
-                for import_name in names {
-                    let module_name = import_name.node.name.to_string();
-                    parsed_module.imports.push(module_name);
-                }
+    match &args.command {
+        None => trace(&project, &args, &settings),
+        Some(Command::Export {
+            sqlite,
+            graphml,
+            gexf,
+            graph,
+        }) => {
+            if let Some(path) = sqlite {
+                export_sqlite(path, &project.graph)?;
             }
-            ast::StmtKind::ImportFrom {
-                level,
-                module,
-                names,
-            } => {
-                let module_name = match &module {
-                    Some(module) => module.to_string(),
-                    None => ".".repeat(level.unwrap_or(0) as usize),
-                };
-                parsed_module.import_froms.push((
-                    module_name,
-                    names.iter().map(|n| n.node.name.to_string()).collect(),
-                ));
+            if let Some(path) = graphml {
+                let mut out = BufWriter::new(File::create(path)?);
+                write_graphml(&mut out, &project.graph, *graph)?;
+                out.flush()?;
             }
-            ast::StmtKind::FunctionDef {
-                name,
-                args: _,
-                body,
-                decorator_list: _,
-                returns: _,
-                type_comment: _,
+            if let Some(path) = gexf {
+                let mut out = BufWriter::new(File::create(path)?);
+                write_gexf(&mut out, &project.graph, *graph)?;
+                out.flush()?;
             }
-            | ast::StmtKind::AsyncFunctionDef {
-                name,
-                args: _,
-                body,
-                decorator_list: _,
-                returns: _,
-                type_comment: _,
-            } => {
-                let mut calls = vec![];
-                for stmt in body {
-                    calls.append(&mut find_calls_in_stmt(&stmt.node));
-                }
-                parsed_module.functions.push((name.to_string(), calls));
-            }
-            ast::StmtKind::ClassDef {
-                name: class_name,
-                bases,
-                body,
-                keywords: _,
-                decorator_list: _,
-            } => {
-                let mut methods = vec![];
-                for stmt in body {
-                    match &stmt.node {
-                        ast::StmtKind::FunctionDef {
-                            name,
-                            args,
-                            body,
-                            decorator_list,
-                            returns,
-                            type_comment,
-                        }
-                        | ast::StmtKind::AsyncFunctionDef {
-                            name,
-                            args,
-                            body,
-                            decorator_list,
-                            returns,
-                            type_comment,
-                        } => {
-                            let mut calls = vec![];
-                            for stmt in body {
-                                calls.append(&mut find_calls_in_stmt(&stmt.node));
-                            }
-                            methods.push((name.to_string(), calls));
-                        }
-                        _ => {}
+            Ok(())
+        }
+        Some(Command::Cycles {
+            baseline,
+            update_baseline,
+        }) => cycles(&project, baseline.as_deref(), *update_baseline),
+        Some(Command::Path {
+            from,
+            to,
+            all,
+            limit,
+        }) => path(&project, from, to, *all, *limit),
+        Some(Command::DeadCode {
+            entry_point,
+            whitelist,
+            min_confidence,
+        }) => {
+            let mut entry_points = settings.entry_points.clone();
+            entry_points.extend(entry_point.iter().cloned());
+            let whitelist = whitelist.as_ref().or(settings.dead_code_whitelist.as_ref());
+            dead_code(&project, &entry_points, whitelist, *min_confidence)
+        }
+        Some(Command::Check { transitive }) => check(&project, &settings.contracts, *transitive),
+        Some(Command::UnusedImports { fix }) => unused_imports(&project, *fix),
+        Some(Command::Report { report }) => {
+            let out = &mut io::stdout().lock();
+            match report {
+                Report::Table { csv } => write_usage_table(out, &project.graph, *csv),
+                Report::Dsm { csv: false } => Dsm::build(&project.graph).write_text(out),
+                Report::Dsm { csv: true } => Dsm::build(&project.graph).write_csv(out),
+                Report::Metrics {
+                    depth,
+                    sort,
+                    csv,
+                    json,
+                } => {
+                    let mut metrics = ProjectMetrics::build(&project, *depth);
+                    metrics.sort(*sort);
+                    if *json {
+                        metrics.write_json(out)
+                    } else if *csv {
+                        metrics.write_csv(out)
+                    } else {
+                        metrics.write_text(out)
                     }
                 }
-                parsed_module.classes.push(PyClass {
-                    name: class_name.to_string(),
-                    methods,
-                });
             }
-            _ => {}
         }
     }
-    parsed_module
 }
 
-fn get_python_paths(dir: &Path) -> io::Result<Vec<PathBuf>> {
-    let mut paths = Vec::new();
-    if dir.is_dir() {
-        for entry in fs::read_dir(dir)? {
-            let path = entry?.path();
-            if path.is_dir() {
-                paths.extend(get_python_paths(&path)?);
-            } else if let Some(extension) = path.extension() {
-                if extension == "py" {
-                    paths.push(path);
-                }
-            }
-        }
+fn cycles(project: &Project, baseline: Option<&Path>, update_baseline: bool) -> io::Result<()> {
+    let cycles = find_import_cycles(&project.graph);
+    if let (Some(path), true) = (baseline, update_baseline) {
+        CycleBaseline::save(path, &cycles)?;
+        println!("wrote {} cycles to {}", cycles.len(), path.display());
+        return Ok(());
     }
-    Ok(paths)
-}
 
-fn get_module_name(path: &Path) -> String {
-    let stem = path.file_stem().unwrap().to_str().unwrap().to_string();
-    let parent = path.parent().unwrap().to_str().unwrap().replace("/", ".");
-    format!("{}.{}", parent, stem)
+    let baseline = baseline.map(CycleBaseline::load).transpose()?;
+    write_cycles(
+        &mut io::stdout().lock(),
+        &project.graph,
+        &cycles,
+        baseline.as_ref(),
+    )?;
+    let new_cycles = cycles
+        .iter()
+        .filter(|cycle| !cycle.deferred)
+        .filter(|cycle| {
+            baseline
+                .as_ref()
+                .is_none_or(|baseline| baseline.is_new(cycle))
+        })
+        .count();
+    if new_cycles > 0 {
+        return Err(io::Error::other(format!(
+            "new import cycles: {}",
+            new_cycles
+        )));
+    }
+    Ok(())
 }
 
-fn build_module_to_paths(base_path: &Path) -> io::Result<HashMap<String, PathBuf>> {
-    let python_paths = get_python_paths(base_path)?;
-
-    // build lookup table from python modules to paths
-    let mut modules_to_paths = HashMap::new();
-    for path in python_paths {
-        let relative_path = path.strip_prefix(base_path).unwrap();
-        if relative_path.file_name().unwrap() == "__init__.py" {
-            modules_to_paths.insert(
-                relative_path
-                    .parent()
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .replace("/", "."),
-                path,
-            );
-        } else {
-            modules_to_paths.insert(get_module_name(&relative_path), path);
-        }
+fn dead_code(
+    project: &Project,
+    entry_points: &[String],
+    whitelist: Option<&PathBuf>,
+    min_confidence: Confidence,
+) -> io::Result<()> {
+    for dir in find_unparsed_test_dirs(project) {
+        eprintln!(
+            "warning: {} is outside the source roots, so code only its tests use is \
+             reported as unused. Add it with --root",
+            dir.display()
+        );
     }
-    Ok(modules_to_paths)
+    let entry_points = find_entry_points(project, entry_points)?;
+    let whitelist = whitelist.map(|path| Whitelist::load(path)).transpose()?;
+    let mut dead = find_dead_code(project, &entry_points);
+    dead.retain(|finding| {
+        let fqn = &project.graph.node(finding.node).fqn;
+        finding.confidence >= min_confidence
+            && whitelist
+                .as_ref()
+                .is_none_or(|whitelist| !whitelist.contains(fqn))
+    });
+    write_dead_code(&mut io::stdout().lock(), &project.graph, &dead)
 }
 
-#[derive(Parser)]
-#[command(author, version, about, long_about = None)]
-struct Args {
-    /// The project to analyze
-    #[arg(short, long)]
-    project: PathBuf,
-
-    /// The module to analyze
-    #[arg(short, long)]
-    module: String,
-
-    /// Optional function to analyze
-    #[arg(short, long)]
-    function: Option<String>,
+fn check(project: &Project, contracts: &[Contract], transitive: bool) -> io::Result<()> {
+    if contracts.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "no contracts in the config",
+        ));
+    }
+    let violations = check_contracts(&project.graph, contracts, transitive)?;
+    write_violations(
+        &mut io::stdout().lock(),
+        &project.graph,
+        contracts,
+        &violations,
+    )?;
+    let broken: HashSet<usize> = violations
+        .iter()
+        .map(|violation| violation.contract)
+        .collect();
+    if !broken.is_empty() {
+        return Err(io::Error::other(format!(
+            "broken contracts: {}",
+            broken.len()
+        )));
+    }
+    Ok(())
+}
 
-    #[arg(long)]
-    debug: bool,
+fn unused_imports(project: &Project, fix: bool) -> io::Result<()> {
+    let out = &mut io::stdout().lock();
+    let mut remaining = vec![];
+    let mut removed = 0;
+    let mut files = 0;
+    for module in project.modules.values() {
+        let mut unused = find_unused_imports(module);
+        if fix && !unused.is_empty() {
+            let fixed = remove_unused_imports(module, &unused)?;
+            if !fixed.is_empty() {
+                removed += fixed.len();
+                files += 1;
+            }
+            unused.retain(|import| {
+                !fixed
+                    .iter()
+                    .any(|fixed| (fixed.location, &fixed.name) == (import.location, &import.name))
+            });
+        }
+        remaining.append(&mut unused);
+    }
+    if fix {
+        writeln!(
+            out,
+            "removed {} unused imports from {} {}",
+            removed,
+            files,
+            if files == 1 { "file" } else { "files" }
+        )?;
+        if !remaining.is_empty() {
+            writeln!(out, "left alone, as they share a line with other code:")?;
+        }
+    }
+    if remaining.is_empty() {
+        if !fix {
+            writeln!(out, "no unused imports")?;
+        }
+        return Ok(());
+    }
+    write_unused_imports(out, &remaining)?;
+    Err(io::Error::other(format!(
+        "unused imports: {}",
+        remaining.len()
+    )))
 }
 
-fn main() -> io::Result<()> {
-    let args: Args = Args::parse();
-    let base_path = args.project;
-    let module_name = args.module;
-    let function_name = args.function;
-    let debug = args.debug;
+fn path(project: &Project, from: &str, to: &str, all: bool, limit: usize) -> io::Result<()> {
+    let graph = &project.graph;
+    let find = |fqn: &str| {
+        graph.find(fqn).ok_or_else(|| {
+            let names = graph.nodes().iter().map(|node| node.fqn.as_str());
+            not_found("unknown symbol", fqn, names)
+        })
+    };
+    let (from_id, to_id) = (find(from)?, find(to)?);
+    // modules are connected by imports and everything else by calls, so there
+    // is no path from one to the other
+    let is_module = |id| graph.node(id).kind == NodeKind::Module;
+    if is_module(from_id) != is_module(to_id) {
+        let (module, other) = if is_module(from_id) {
+            (from, to)
+        } else {
+            (to, from)
+        };
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "paths between a module and a function aren't supported: {} is a module and {} isn't",
+                module, other
+            ),
+        ));
+    }
+    if from_id == to_id {
+        writeln!(io::stdout().lock(), "{}", from)?;
+        return Ok(());
+    }
+    let kinds = if is_module(from_id) {
+        MODULE_EDGES
+    } else {
+        FUNCTION_EDGES
+    };
+    let paths = if all {
+        graph.simple_paths(from_id, to_id, kinds, limit)
+    } else {
+        graph
+            .shortest_path(from_id, to_id, kinds)
+            .into_iter()
+            .collect()
+    };
+    if paths.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} does not depend on {}", from, to),
+        ));
+    }
+    write_paths(&mut io::stdout().lock(), graph, &paths)
+}
 
-    let modules_to_paths = build_module_to_paths(&base_path)?;
+// Print the dependencies of --module and --function, or of the whole project
+fn trace(project: &Project, args: &Args, settings: &Settings) -> io::Result<()> {
+    let module_name = &args
+        .module
+        .as_deref()
+        .map(|module| project.resolve_module(module))
+        .transpose()?;
+    let function_name = &args.function;
 
-    let mut modules = HashMap::new();
+    // the node to trace from and the edges to follow, or None for the whole project
+    let trace = match (module_name, function_name) {
+        (Some(module_name), Some(function_name)) => Some((
+            project.resolve_function(module_name, function_name)?,
+            FUNCTION_EDGES,
+        )),
+        (Some(module_name), None) => Some((module_name.to_string(), MODULE_EDGES)),
+        (None, _) => None,
+    };
+    let selection = match &trace {
+        Some((root, kinds)) => {
+            let id = project.graph.find(root).unwrap();
+            project.graph.select_reachable(id, kinds)
+        }
+        None => project.graph.select_all(),
+    };
+    let root = trace.as_ref().map(|(root, _)| root.as_str());
 
-    for (module_name, path) in modules_to_paths.iter() {
-        let mut source_code = String::new();
-        File::open(path)?.read_to_string(&mut source_code)?;
-        let module = parse_module(&module_name, &source_code, path);
-        modules.insert(module_name.to_string(), module);
-    }
-    if debug {
-        println!("{:#?}", modules);
+    if args.show_unresolved {
+        let out = &mut io::stdout().lock();
+        let traced: HashSet<&str> = selection
+            .nodes
+            .iter()
+            .map(|&id| project.graph.node(id).fqn.as_str())
+            .collect();
+        return match (&module_name, &function_name) {
+            (Some(_), Some(_)) => write_unresolved(out, &project.graph, |node| {
+                traced.contains(node.fqn.as_str())
+            }),
+            (Some(module_name), None) => {
+                write_unresolved(out, &project.graph, |node| &node.module == module_name)
+            }
+            (None, _) => write_unresolved(out, &project.graph, |_| true),
+        };
     }
 
-    print_transitive_deps(&modules, &module_name, function_name.as_deref())?;
+    let tree = |elide_repeats: bool| {
+        let Some(module_name) = &module_name else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("--module is required for {} output", settings.format),
+            ));
+        };
+        let options = TreeOptions {
+            max_depth: args.max_depth,
+            elide_repeats,
+        };
+        match &function_name {
+            Some(function_name) => {
+                project.function_dependencies(module_name, function_name, &options)
+            }
+            None => project.module_dependencies(module_name, &options),
+        }
+    };
+
+    let out = &mut io::stdout().lock();
+    match settings.format {
+        Format::Text if module_name.is_none() => write_project_summary(out, &project.graph)?,
+        Format::Text => {
+            let tree = tree(args.elide_repeats)?;
+            write_transitive_deps(out, &tree)?;
+            if args.elide_repeats {
+                writeln!(out, "{} unique dependencies", tree.unique_dependencies())?;
+            }
+        }
+        // a flowchart draws each node once anyway, and expanding repeats can
+        // grow the tree exponentially
+        Format::Mermaid => write_mermaid(out, &tree(true)?, args.max_depth)?,
+        Format::Json => write_json(out, &project.graph, &selection, root)?,
+        Format::Ndjson => {
+            let out = &mut BufWriter::new(out);
+            write_ndjson(out, &project.graph, &selection, root)?;
+            out.flush()?;
+        }
+        Format::Dot => write_dot(out, &project.graph, &selection, root)?,
+        Format::Html => write_html(out, &project.graph, &selection, root)?,
+    }
 
     Ok(())
 }
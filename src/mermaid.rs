@@ -0,0 +1,86 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, Write},
+};
+
+use crate::tree::DependencyTree;
+
+/// Write a dependency tree as a Mermaid flowchart. Nodes that appear more than
+/// once in the tree are drawn once, and nothing deeper than `max_depth` levels
+/// below the root is drawn
+pub fn write_mermaid(
+    out: &mut impl Write,
+    tree: &DependencyTree,
+    max_depth: Option<usize>,
+) -> io::Result<()> {
+    let mut ids = NodeIds::default();
+    let mut edges = vec![];
+    let mut seen_edges = HashSet::new();
+    collect_edges(tree, 0, max_depth, &mut ids, &mut edges, &mut seen_edges);
+
+    writeln!(out, "flowchart LR")?;
+    for (name, id) in &ids.order {
+        writeln!(out, "    {}[\"{}\"]", id, escape_label(name))?;
+    }
+    for (from, to) in edges {
+        writeln!(out, "    {} --> {}", from, to)?;
+    }
+    Ok(())
+}
+
+fn collect_edges(
+    tree: &DependencyTree,
+    depth: usize,
+    max_depth: Option<usize>,
+    ids: &mut NodeIds,
+    edges: &mut Vec<(String, String)>,
+    seen_edges: &mut HashSet<(String, String)>,
+) {
+    let from = ids.get(&tree.name);
+    if max_depth.is_some_and(|max_depth| depth >= max_depth) {
+        return;
+    }
+    for dependency in &tree.dependencies {
+        let to = ids.get(&dependency.name);
+        let edge = (from.to_string(), to);
+        if seen_edges.insert(edge.clone()) {
+            edges.push(edge);
+        }
+        collect_edges(dependency, depth + 1, max_depth, ids, edges, seen_edges);
+    }
+}
+
+// Mermaid ids can only hold letters, digits, and underscores, so dotted names
+// are squashed and given a suffix if two of them end up the same
+#[derive(Default)]
+struct NodeIds {
+    ids: HashMap<String, String>,
+    taken: HashSet<String>,
+    order: Vec<(String, String)>,
+}
+
+impl NodeIds {
+    fn get(&mut self, name: &str) -> String {
+        if let Some(id) = self.ids.get(name) {
+            return id.to_string();
+        }
+        let base: String = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let mut id = format!("n_{}", base);
+        let mut suffix = 1;
+        while self.taken.contains(&id) {
+            suffix += 1;
+            id = format!("n_{}_{}", base, suffix);
+        }
+        self.taken.insert(id.to_string());
+        self.ids.insert(name.to_string(), id.to_string());
+        self.order.push((name.to_string(), id.to_string()));
+        id
+    }
+}
+
+fn escape_label(label: &str) -> String {
+    label.replace('"', "#quot;")
+}
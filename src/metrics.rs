@@ -0,0 +1,389 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{self, Write},
+};
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::{
+    graph::EdgeKind,
+    project::{Project, PyClass},
+    table::Table,
+};
+
+/// Which column to order the metrics by. Names sort alphabetically, and
+/// numbers largest first, with missing values last
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum MetricsSort {
+    Name,
+    Afferent,
+    Efferent,
+    Instability,
+    Abstractness,
+    #[default]
+    Distance,
+}
+
+/// Coupling and stability of a module or package, after Robert Martin's
+/// package metrics. Only imports between project modules count, including
+/// imports inside functions
+#[derive(Debug, Clone, Serialize)]
+pub struct Metrics {
+    pub name: String,
+    pub modules: usize,
+    pub classes: usize,
+    pub abstract_classes: usize,
+    /// Afferent coupling: how many others import this one
+    pub afferent: usize,
+    /// Efferent coupling: how many others this one imports
+    pub efferent: usize,
+    /// Efferent / (afferent + efferent), from 0 for stable to 1 for unstable.
+    /// Missing when nothing is imported either way
+    pub instability: Option<f64>,
+    /// The share of classes that are abstract, or 0 without classes
+    pub abstractness: f64,
+    /// How far abstractness + instability is from 1. Far from 0 means
+    /// concrete code many depend on, or abstract code nobody uses
+    pub distance: Option<f64>,
+}
+
+/// Metrics for every module, and for the packages formed by the first
+/// `depth` parts of module names
+#[derive(Debug, Serialize)]
+pub struct ProjectMetrics {
+    pub depth: usize,
+    pub modules: Vec<Metrics>,
+    pub packages: Vec<Metrics>,
+}
+
+impl ProjectMetrics {
+    pub fn build(project: &Project, depth: usize) -> ProjectMetrics {
+        let depth = depth.max(1);
+        let package_of = |module: &str| {
+            let parts: Vec<&str> = module.split('.').take(depth).collect();
+            parts.join(".")
+        };
+        ProjectMetrics {
+            depth,
+            modules: metrics(project, |module| module.to_string()),
+            packages: metrics(project, package_of),
+        }
+    }
+
+    pub fn sort(&mut self, by: MetricsSort) {
+        sort(&mut self.modules, by);
+        sort(&mut self.packages, by);
+    }
+
+    pub fn write_text(&self, out: &mut impl Write) -> io::Result<()> {
+        metrics_table("module", &self.modules, false).write_text(out)?;
+        writeln!(out)?;
+        let title = format!("package (depth {})", self.depth);
+        metrics_table(&title, &self.packages, true).write_text(out)
+    }
+
+    pub fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut table = Table::new(&[
+            "level",
+            "name",
+            "modules",
+            "classes",
+            "abstract_classes",
+            "afferent",
+            "efferent",
+            "instability",
+            "abstractness",
+            "distance",
+        ]);
+        for (level, metrics) in [("module", &self.modules), ("package", &self.packages)] {
+            for metrics in metrics {
+                table.push(vec![
+                    level.to_string(),
+                    metrics.name.to_string(),
+                    metrics.modules.to_string(),
+                    metrics.classes.to_string(),
+                    metrics.abstract_classes.to_string(),
+                    metrics.afferent.to_string(),
+                    metrics.efferent.to_string(),
+                    format_ratio(metrics.instability),
+                    format_ratio(Some(metrics.abstractness)),
+                    format_ratio(metrics.distance),
+                ]);
+            }
+        }
+        table.write_csv(out)
+    }
+
+    pub fn write_json(&self, out: &mut impl Write) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut *out, self)?;
+        writeln!(out)
+    }
+}
+
+// Metrics for the groups of project modules `group_of` puts together, sorted
+// by name. Imports within a group don't count
+fn metrics(project: &Project, group_of: impl Fn(&str) -> String) -> Vec<Metrics> {
+    let mut groups: BTreeMap<String, Metrics> = BTreeMap::new();
+    for module in project.modules.values() {
+        let group = groups
+            .entry(group_of(&module.name))
+            .or_insert_with_key(|name| Metrics {
+                name: name.to_string(),
+                modules: 0,
+                classes: 0,
+                abstract_classes: 0,
+                afferent: 0,
+                efferent: 0,
+                instability: None,
+                abstractness: 0.0,
+                distance: None,
+            });
+        group.modules += 1;
+        group.classes += module.classes.len();
+        group.abstract_classes += module
+            .classes
+            .iter()
+            .filter(|class| is_abstract(class))
+            .count();
+    }
+
+    let graph = &project.graph;
+    let mut imports: BTreeSet<(String, String)> = BTreeSet::new();
+    for edge in graph.edges() {
+        let to = graph.node(edge.to);
+        if edge.kind != EdgeKind::Import || to.external {
+            continue;
+        }
+        let (from, to) = (group_of(&graph.node(edge.from).module), group_of(&to.fqn));
+        if from != to {
+            imports.insert((from, to));
+        }
+    }
+    for (from, to) in &imports {
+        if let Some(from) = groups.get_mut(from) {
+            from.efferent += 1;
+        }
+        if let Some(to) = groups.get_mut(to) {
+            to.afferent += 1;
+        }
+    }
+
+    let mut metrics: Vec<Metrics> = groups.into_values().collect();
+    for metrics in &mut metrics {
+        let coupling = metrics.afferent + metrics.efferent;
+        metrics.instability = (coupling > 0).then(|| metrics.efferent as f64 / coupling as f64);
+        if metrics.classes > 0 {
+            metrics.abstractness = metrics.abstract_classes as f64 / metrics.classes as f64;
+        }
+        metrics.distance = metrics
+            .instability
+            .map(|instability| (metrics.abstractness + instability - 1.0).abs());
+    }
+    metrics
+}
+
+// Whether a class is an abstract base class or a protocol, or declares an
+// abstract method
+fn is_abstract(class: &PyClass) -> bool {
+    let last = |name: &str| name.rsplit('.').next().unwrap_or_default().to_string();
+    class
+        .bases
+        .iter()
+        .any(|base| matches!(last(&base.name).as_str(), "ABC" | "Protocol"))
+        || class.methods.iter().any(|method| {
+            method
+                .decorators
+                .iter()
+                .any(|decorator| last(&decorator.name).starts_with("abstract"))
+        })
+}
+
+fn sort(metrics: &mut [Metrics], by: MetricsSort) {
+    let key = |metrics: &Metrics| -> Option<f64> {
+        match by {
+            MetricsSort::Name => None,
+            MetricsSort::Afferent => Some(metrics.afferent as f64),
+            MetricsSort::Efferent => Some(metrics.efferent as f64),
+            MetricsSort::Instability => metrics.instability,
+            MetricsSort::Abstractness => Some(metrics.abstractness),
+            MetricsSort::Distance => metrics.distance,
+        }
+    };
+    if by == MetricsSort::Name {
+        metrics.sort_by(|a, b| a.name.cmp(&b.name));
+        return;
+    }
+    // largest first, ties by name
+    metrics.sort_by(|a, b| {
+        let (a_key, b_key) = (key(a).unwrap_or(-1.0), key(b).unwrap_or(-1.0));
+        b_key.total_cmp(&a_key).then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+// Aligned columns of metrics. Modules are each one module, so the count of
+// modules is only shown for packages
+fn metrics_table(title: &str, metrics: &[Metrics], count_modules: bool) -> Table {
+    let mut header = vec![title];
+    if count_modules {
+        header.push("modules");
+    }
+    header.extend([
+        "classes",
+        "ca",
+        "ce",
+        "instability",
+        "abstractness",
+        "distance",
+    ]);
+    let mut table = Table::new(&header);
+    for column in 1..header.len() {
+        table = table.align_right(column);
+    }
+    for metrics in metrics {
+        let mut row = vec![metrics.name.to_string()];
+        if count_modules {
+            row.push(metrics.modules.to_string());
+        }
+        row.extend([
+            metrics.classes.to_string(),
+            metrics.afferent.to_string(),
+            metrics.efferent.to_string(),
+            format_ratio(metrics.instability),
+            format_ratio(Some(metrics.abstractness)),
+            format_ratio(metrics.distance),
+        ]);
+        table.push(row);
+    }
+    table
+}
+
+fn format_ratio(ratio: Option<f64>) -> String {
+    ratio.map_or_else(|| "-".to_string(), |ratio| format!("{:.2}", ratio))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::project;
+
+    const BASE: &str = "\
+from abc import ABC, abstractmethod
+
+class Repository(ABC):
+    pass
+
+class Store:
+    @abstractmethod
+    def get(self): ...
+
+class Memory(Store):
+    def get(self):
+        return 1
+";
+
+    fn project_metrics() -> ProjectMetrics {
+        ProjectMetrics::build(
+            &project(&[
+                (
+                    "app/main.py",
+                    "import app.views\nimport core.base\nimport core.util\n",
+                ),
+                ("app/views.py", "import core.base\n"),
+                ("core/base.py", BASE),
+                ("core/util.py", ""),
+                ("lone.py", ""),
+            ]),
+            1,
+        )
+    }
+
+    // name, afferent, efferent, instability, abstractness, distance
+    type Row = (String, usize, usize, Option<f64>, f64, Option<f64>);
+
+    fn rows(metrics: &[Metrics]) -> Vec<Row> {
+        metrics
+            .iter()
+            .map(|m| {
+                (
+                    m.name.to_string(),
+                    m.afferent,
+                    m.efferent,
+                    m.instability,
+                    m.abstractness,
+                    m.distance,
+                )
+            })
+            .collect()
+    }
+
+    fn row(name: &str, ca: usize, ce: usize, i: Option<f64>, a: f64, d: Option<f64>) -> Row {
+        (name.to_string(), ca, ce, i, a, d)
+    }
+
+    #[test]
+    fn module_metrics() {
+        let metrics = project_metrics();
+        let base = &metrics.modules[2];
+        assert_eq!((base.classes, base.abstract_classes), (3, 2));
+        assert_eq!(
+            rows(&metrics.modules),
+            [
+                row("app.main", 0, 3, Some(1.0), 0.0, Some(0.0)),
+                row("app.views", 1, 1, Some(0.5), 0.0, Some(0.5)),
+                row(
+                    "core.base",
+                    2,
+                    0,
+                    Some(0.0),
+                    2.0 / 3.0,
+                    Some(1.0 - 2.0 / 3.0)
+                ),
+                row("core.util", 1, 0, Some(0.0), 0.0, Some(1.0)),
+                row("lone", 0, 0, None, 0.0, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn package_metrics_leave_out_imports_inside_a_package() {
+        let metrics = project_metrics();
+        assert_eq!(
+            rows(&metrics.packages),
+            [
+                row("app", 0, 1, Some(1.0), 0.0, Some(0.0)),
+                row("core", 1, 0, Some(0.0), 2.0 / 3.0, Some(1.0 - 2.0 / 3.0)),
+                row("lone", 0, 0, None, 0.0, None),
+            ]
+        );
+        assert_eq!(metrics.packages[1].modules, 2);
+    }
+
+    #[test]
+    fn sorted_largest_first_with_missing_values_last() {
+        let mut metrics = project_metrics();
+        let names = |metrics: &[Metrics]| -> Vec<String> {
+            metrics.iter().map(|m| m.name.to_string()).collect()
+        };
+
+        metrics.sort(MetricsSort::Distance);
+        assert_eq!(
+            names(&metrics.modules),
+            ["core.util", "app.views", "core.base", "app.main", "lone"]
+        );
+        assert_eq!(names(&metrics.packages), ["core", "app", "lone"]);
+
+        // ties go by name
+        metrics.sort(MetricsSort::Afferent);
+        assert_eq!(
+            names(&metrics.modules),
+            ["core.base", "app.views", "core.util", "app.main", "lone"]
+        );
+
+        metrics.sort(MetricsSort::Name);
+        assert_eq!(
+            names(&metrics.modules),
+            ["app.main", "app.views", "core.base", "core.util", "lone"]
+        );
+    }
+}
@@ -0,0 +1,280 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::{self, Write},
+};
+
+use clap::ValueEnum;
+
+use crate::graph::{DependencyGraph, EdgeKind, NodeId, NodeKind, MODULE_EDGES};
+
+/// Which part of the dependency graph to export for network analysis tools
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum NetworkView {
+    /// Modules and the imports between them
+    #[default]
+    Modules,
+    /// Functions, methods, and classes, and the calls and instantiations
+    /// between them
+    Calls,
+}
+
+// The nodes of one view, and its edges merged so each pair of nodes is
+// joined once per kind, counting how many times the dependency appears
+struct Network {
+    nodes: Vec<NodeId>,
+    edges: Vec<NetworkEdge>,
+}
+
+struct NetworkEdge {
+    from: NodeId,
+    to: NodeId,
+    kind: EdgeKind,
+    count: usize,
+}
+
+fn network(graph: &DependencyGraph, view: NetworkView) -> Network {
+    let (node_kinds, edge_kinds): (&[NodeKind], &[EdgeKind]) = match view {
+        NetworkView::Modules => (&[NodeKind::Module], MODULE_EDGES),
+        NetworkView::Calls => (
+            &[NodeKind::Function, NodeKind::Method, NodeKind::Class],
+            &[EdgeKind::Call, EdgeKind::Instantiate],
+        ),
+    };
+
+    let mut counts: BTreeMap<(NodeId, NodeId, EdgeKind), usize> = BTreeMap::new();
+    for edge in graph.edges() {
+        if !edge_kinds.contains(&edge.kind) {
+            continue;
+        }
+        // imports inside functions are counted as imports by their module
+        let from = match view {
+            NetworkView::Modules => graph.find(&graph.node(edge.from).module).unwrap(),
+            NetworkView::Calls => edge.from,
+        };
+        *counts.entry((from, edge.to, edge.kind)).or_default() += 1;
+    }
+
+    // calls made at the top level of a module come from the module, and calls
+    // to things outside the project go to symbols, so those are kept too
+    let endpoints: HashSet<NodeId> = counts
+        .keys()
+        .flat_map(|&(from, to, _)| [from, to])
+        .collect();
+    let nodes = (0..graph.nodes().len())
+        .filter(|id| node_kinds.contains(&graph.node(*id).kind) || endpoints.contains(id))
+        .collect();
+    let edges = counts
+        .into_iter()
+        .map(|((from, to, kind), count)| NetworkEdge {
+            from,
+            to,
+            kind,
+            count,
+        })
+        .collect();
+    Network { nodes, edges }
+}
+
+/// Write the modules or the call graph as GraphML, for yEd, Gephi, or networkx
+pub fn write_graphml(
+    out: &mut impl Write,
+    graph: &DependencyGraph,
+    view: NetworkView,
+) -> io::Result<()> {
+    let network = network(graph, view);
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://graphml.graphdrawing.org/xmlns http://graphml.graphdrawing.org/xmlns/1.0/graphml.xsd">"#
+    )?;
+    for (id, target, name, kind) in [
+        ("label", "node", "label", "string"),
+        ("kind", "node", "kind", "string"),
+        ("module", "node", "module", "string"),
+        ("package", "node", "package", "string"),
+        ("loc", "node", "loc", "int"),
+        ("external", "node", "external", "boolean"),
+        ("edge_kind", "edge", "kind", "string"),
+        ("count", "edge", "count", "int"),
+    ] {
+        writeln!(
+            out,
+            r#"  <key id="{}" for="{}" attr.name="{}" attr.type="{}"/>"#,
+            id, target, name, kind
+        )?;
+    }
+    writeln!(
+        out,
+        r#"  <graph id="{}" edgedefault="directed">"#,
+        view_name(view)
+    )?;
+    for &id in &network.nodes {
+        let node = graph.node(id);
+        writeln!(out, r#"    <node id="n{}">"#, id)?;
+        writeln!(
+            out,
+            r#"      <data key="label">{}</data>"#,
+            escape(&node.fqn)
+        )?;
+        writeln!(
+            out,
+            r#"      <data key="kind">{}</data>"#,
+            node.kind.as_str()
+        )?;
+        writeln!(
+            out,
+            r#"      <data key="module">{}</data>"#,
+            escape(&node.module)
+        )?;
+        if let Some(package) = node.package() {
+            writeln!(
+                out,
+                r#"      <data key="package">{}</data>"#,
+                escape(package)
+            )?;
+        }
+        if let Some(lines) = node.lines {
+            writeln!(out, r#"      <data key="loc">{}</data>"#, lines)?;
+        }
+        writeln!(
+            out,
+            r#"      <data key="external">{}</data>"#,
+            node.external
+        )?;
+        writeln!(out, "    </node>")?;
+    }
+    for (index, edge) in network.edges.iter().enumerate() {
+        writeln!(
+            out,
+            r#"    <edge id="e{}" source="n{}" target="n{}">"#,
+            index, edge.from, edge.to
+        )?;
+        writeln!(
+            out,
+            r#"      <data key="edge_kind">{}</data>"#,
+            edge.kind.as_str()
+        )?;
+        writeln!(out, r#"      <data key="count">{}</data>"#, edge.count)?;
+        writeln!(out, "    </edge>")?;
+    }
+    writeln!(out, "  </graph>")?;
+    writeln!(out, "</graphml>")
+}
+
+/// Write the modules or the call graph as GEXF, for Gephi. Edge weights are
+/// how many times the dependency appears
+pub fn write_gexf(
+    out: &mut impl Write,
+    graph: &DependencyGraph,
+    view: NetworkView,
+) -> io::Result<()> {
+    let network = network(graph, view);
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(out, r#"<gexf xmlns="http://gexf.net/1.3" version="1.3">"#)?;
+    writeln!(out, "  <meta>")?;
+    writeln!(out, "    <creator>pydep</creator>")?;
+    writeln!(out, "    <description>{}</description>", view_name(view))?;
+    writeln!(out, "  </meta>")?;
+    writeln!(out, r#"  <graph defaultedgetype="directed" mode="static">"#)?;
+    writeln!(out, r#"    <attributes class="node">"#)?;
+    for (id, (title, kind)) in [
+        ("kind", "string"),
+        ("module", "string"),
+        ("package", "string"),
+        ("loc", "integer"),
+        ("external", "boolean"),
+    ]
+    .iter()
+    .enumerate()
+    {
+        writeln!(
+            out,
+            r#"      <attribute id="{}" title="{}" type="{}"/>"#,
+            id, title, kind
+        )?;
+    }
+    writeln!(out, "    </attributes>")?;
+    writeln!(out, r#"    <attributes class="edge">"#)?;
+    writeln!(
+        out,
+        r#"      <attribute id="0" title="kind" type="string"/>"#
+    )?;
+    writeln!(
+        out,
+        r#"      <attribute id="1" title="count" type="integer"/>"#
+    )?;
+    writeln!(out, "    </attributes>")?;
+
+    writeln!(out, "    <nodes>")?;
+    for &id in &network.nodes {
+        let node = graph.node(id);
+        writeln!(
+            out,
+            r#"      <node id="n{}" label="{}">"#,
+            id,
+            escape(&node.fqn)
+        )?;
+        writeln!(out, "        <attvalues>")?;
+        let mut values = vec![
+            (0, node.kind.as_str().to_string()),
+            (1, node.module.to_string()),
+        ];
+        if let Some(package) = node.package() {
+            values.push((2, package.to_string()));
+        }
+        if let Some(lines) = node.lines {
+            values.push((3, lines.to_string()));
+        }
+        values.push((4, node.external.to_string()));
+        for (attribute, value) in values {
+            writeln!(
+                out,
+                r#"          <attvalue for="{}" value="{}"/>"#,
+                attribute,
+                escape(&value)
+            )?;
+        }
+        writeln!(out, "        </attvalues>")?;
+        writeln!(out, "      </node>")?;
+    }
+    writeln!(out, "    </nodes>")?;
+
+    writeln!(out, "    <edges>")?;
+    for (index, edge) in network.edges.iter().enumerate() {
+        writeln!(
+            out,
+            r#"      <edge id="e{}" source="n{}" target="n{}" weight="{}">"#,
+            index, edge.from, edge.to, edge.count
+        )?;
+        writeln!(out, "        <attvalues>")?;
+        writeln!(
+            out,
+            r#"          <attvalue for="0" value="{}"/>"#,
+            edge.kind.as_str()
+        )?;
+        writeln!(
+            out,
+            r#"          <attvalue for="1" value="{}"/>"#,
+            edge.count
+        )?;
+        writeln!(out, "        </attvalues>")?;
+        writeln!(out, "      </edge>")?;
+    }
+    writeln!(out, "    </edges>")?;
+    writeln!(out, "  </graph>")?;
+    writeln!(out, "</gexf>")
+}
+
+fn view_name(view: NetworkView) -> &'static str {
+    match view {
+        NetworkView::Modules => "modules",
+        NetworkView::Calls => "calls",
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
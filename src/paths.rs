@@ -0,0 +1,37 @@
+use std::io::{self, Write};
+
+use crate::graph::DependencyGraph;
+
+/// Write chains of edges from [`DependencyGraph::shortest_path`] or
+/// [`DependencyGraph::simple_paths`], one step per line with the kind of
+/// dependency and where it is
+pub fn write_paths(
+    out: &mut impl Write,
+    graph: &DependencyGraph,
+    paths: &[Vec<usize>],
+) -> io::Result<()> {
+    for (number, path) in paths.iter().enumerate() {
+        if number > 0 {
+            writeln!(out)?;
+        }
+        let Some(&first) = path.first() else {
+            continue;
+        };
+        writeln!(out, "{}", graph.node(graph.edges()[first].from).fqn)?;
+        for &index in path {
+            let edge = &graph.edges()[index];
+            let from = graph.node(edge.from);
+            write!(
+                out,
+                "  -> {}  ({}",
+                graph.node(edge.to).fqn,
+                edge.kind.as_str()
+            )?;
+            if let Some(path) = &from.path {
+                write!(out, " at {}:{}", path.display(), edge.location.line)?;
+            }
+            writeln!(out, ")")?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,898 @@
+use rayon::prelude::*;
+use rustpython_parser::{
+    ast::{self, fold::Fold},
+    parser::parse_program,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+use crate::{
+    cache::{ParseCache, CACHE_DIR_NAME},
+    graph::{DependencyGraph, EdgeKind, FUNCTION_EDGES, MODULE_EDGES},
+    kind_parsing::{
+        expr_to_name, find_all_names, find_calls_in_stmt, find_decorator, find_names_in_annotation,
+        NameCollector,
+    },
+    roots::{find_source_roots, SourceRoot},
+    tree::{DependencyTree, TreeOptions},
+};
+
+/// Everything pydep extracts from one python file
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PyModule {
+    pub name: String,
+    pub path: PathBuf,
+    /// Imports outside functions and classes, including ones in blocks like
+    /// `if` and `try`
+    pub imports: Vec<Import>,
+    pub import_froms: Vec<ImportFrom>,
+    pub functions: Vec<PyFunction>,
+    pub classes: Vec<PyClass>,
+    /// Lines in the file
+    pub lines: usize,
+    /// Calls made at the top level of the module, outside any function or class
+    pub calls: Vec<Reference>,
+    /// Whether the module runs code under `if __name__ == "__main__":`
+    pub main_block: bool,
+    /// Every name the module reads anywhere, including in annotations
+    pub names: BTreeSet<String>,
+    /// The names in `__all__`, if the module sets it
+    pub exports: Option<Vec<String>>,
+}
+
+/// `import module` or `import module as alias`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Import {
+    pub module: String,
+    pub alias: Option<String>,
+    pub location: Location,
+    /// Whether the import is under `if TYPE_CHECKING:`, so it never runs
+    #[serde(default)]
+    pub type_checking: bool,
+}
+
+/// `from module import name, other as alias`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportFrom {
+    /// The module as written, without the leading dots of a relative import
+    pub module: String,
+    /// How many packages up a relative import starts, 0 for absolute imports
+    pub level: usize,
+    pub names: Vec<ImportedName>,
+    pub location: Location,
+    /// Whether the import is under `if TYPE_CHECKING:`, so it never runs
+    #[serde(default)]
+    pub type_checking: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportedName {
+    pub name: String,
+    pub alias: Option<String>,
+}
+
+impl ImportedName {
+    /// The name this import is bound to in the importing module
+    pub fn bound_name(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PyFunction {
+    pub name: String,
+    pub location: Location,
+    /// Lines from the `def` to the end of the body
+    pub lines: usize,
+    pub calls: Vec<Reference>,
+    pub decorators: Vec<Reference>,
+    /// Types named in the argument and return annotations
+    pub annotations: Vec<Reference>,
+    /// Imports inside the body, which only run when the function is called
+    pub imports: Vec<Import>,
+    pub import_froms: Vec<ImportFrom>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PyClass {
+    pub name: String,
+    pub location: Location,
+    /// Lines from the `class` to the end of the body
+    pub lines: usize,
+    pub bases: Vec<Reference>,
+    pub decorators: Vec<Reference>,
+    pub methods: Vec<PyFunction>,
+}
+
+/// A line and column in a source file, both starting at 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl From<ast::Location> for Location {
+    fn from(location: ast::Location) -> Location {
+        Location {
+            line: location.row(),
+            column: location.column() + 1,
+        }
+    }
+}
+
+/// A plain or dotted name used somewhere in the source, like `foo` or `foo.bar.baz`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reference {
+    pub name: String,
+    pub location: Location,
+    /// Looked up by name through a dependency injection container, rather than
+    /// referred to directly
+    pub lookup: bool,
+}
+
+impl Reference {
+    pub fn new(name: &str, location: ast::Location) -> Reference {
+        Reference {
+            name: name.to_string(),
+            location: location.into(),
+            lookup: false,
+        }
+    }
+
+    pub fn lookup(name: &str, location: ast::Location) -> Reference {
+        Reference {
+            lookup: true,
+            ..Reference::new(name, location)
+        }
+    }
+}
+
+impl PyModule {
+    pub fn new(name: &str, path: &Path) -> PyModule {
+        PyModule {
+            name: name.to_string(),
+            path: path.to_path_buf(),
+            imports: vec![],
+            import_froms: vec![],
+            functions: vec![],
+            classes: vec![],
+            lines: 0,
+            calls: vec![],
+            main_block: false,
+            names: BTreeSet::new(),
+            exports: None,
+        }
+    }
+
+    /// Whether this module is a package's __init__.py
+    pub fn is_package(&self) -> bool {
+        self.path.file_stem().is_some_and(|stem| stem == "__init__")
+    }
+}
+
+pub fn parse_module(
+    name: &str,
+    source_code: &str,
+    path: &Path,
+    di_patterns: &[String],
+) -> io::Result<PyModule> {
+    let mut parsed_module = PyModule::new(name, path);
+    parsed_module.lines = source_code.lines().count();
+
+    let ast = parse_program(source_code, &path.to_string_lossy()).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), e),
+        )
+    })?;
+    for located in &ast {
+        if let Some(mut names) = find_all_names(located) {
+            parsed_module
+                .exports
+                .get_or_insert_with(Vec::new)
+                .append(&mut names);
+        }
+        match &located.node {
+            ast::StmtKind::Import { .. } | ast::StmtKind::ImportFrom { .. } => {
+                add_import(
+                    located,
+                    false,
+                    &mut parsed_module.imports,
+                    &mut parsed_module.import_froms,
+                );
+            }
+            ast::StmtKind::FunctionDef { .. } | ast::StmtKind::AsyncFunctionDef { .. } => {
+                if let Some(function) = parse_function(located, di_patterns) {
+                    parsed_module.functions.push(function);
+                }
+            }
+            ast::StmtKind::ClassDef {
+                name: class_name,
+                bases,
+                body,
+                keywords: _,
+                decorator_list,
+            } => {
+                let mut methods = vec![];
+                for stmt in body {
+                    if let Some(method) = parse_function(stmt, di_patterns) {
+                        methods.push(method);
+                    }
+                }
+                parsed_module.classes.push(PyClass {
+                    name: class_name.to_string(),
+                    location: located.location.into(),
+                    lines: line_count(located),
+                    bases: bases
+                        .iter()
+                        .filter_map(|base| {
+                            expr_to_name(base).map(|name| Reference::new(&name, base.location))
+                        })
+                        .collect(),
+                    decorators: decorator_list.iter().filter_map(find_decorator).collect(),
+                    methods,
+                });
+            }
+            _ => {
+                find_imports_in_body(
+                    std::slice::from_ref(located),
+                    false,
+                    false,
+                    &mut parsed_module.imports,
+                    &mut parsed_module.import_froms,
+                );
+                parsed_module.main_block |= is_main_block(located);
+                parsed_module
+                    .calls
+                    .append(&mut find_calls_in_stmt(located, di_patterns));
+            }
+        }
+    }
+
+    let mut names = NameCollector::default();
+    for located in ast {
+        let _ = names.fold_stmt(located);
+    }
+    parsed_module.names = names.names;
+    Ok(parsed_module)
+}
+
+fn parse_function(stmt: &ast::Stmt, di_patterns: &[String]) -> Option<PyFunction> {
+    match &stmt.node {
+        ast::StmtKind::FunctionDef {
+            name,
+            args,
+            body,
+            decorator_list,
+            returns,
+            type_comment: _,
+        }
+        | ast::StmtKind::AsyncFunctionDef {
+            name,
+            args,
+            body,
+            decorator_list,
+            returns,
+            type_comment: _,
+        } => {
+            let mut calls = vec![];
+            for stmt in body {
+                calls.append(&mut find_calls_in_stmt(stmt, di_patterns));
+            }
+
+            let mut annotations = vec![];
+            let all_args = args
+                .posonlyargs
+                .iter()
+                .chain(args.args.iter())
+                .chain(args.vararg.iter().map(|arg| arg.as_ref()))
+                .chain(args.kwonlyargs.iter())
+                .chain(args.kwarg.iter().map(|arg| arg.as_ref()));
+            for arg in all_args {
+                if let Some(annotation) = &arg.node.annotation {
+                    annotations.append(&mut find_names_in_annotation(annotation));
+                }
+            }
+            if let Some(returns) = returns {
+                annotations.append(&mut find_names_in_annotation(returns));
+            }
+
+            let mut imports = vec![];
+            let mut import_froms = vec![];
+            find_imports_in_body(body, true, false, &mut imports, &mut import_froms);
+
+            Some(PyFunction {
+                name: name.to_string(),
+                location: stmt.location.into(),
+                lines: line_count(stmt),
+                calls,
+                decorators: decorator_list.iter().filter_map(find_decorator).collect(),
+                annotations,
+                imports,
+                import_froms,
+            })
+        }
+        _ => None,
+    }
+}
+
+// Add the import `stmt` makes, returning false if it isn't an import
+fn add_import(
+    stmt: &ast::Stmt,
+    type_checking: bool,
+    imports: &mut Vec<Import>,
+    import_froms: &mut Vec<ImportFrom>,
+) -> bool {
+    match &stmt.node {
+        ast::StmtKind::Import { names } => {
+            for import_name in names {
+                imports.push(Import {
+                    module: import_name.node.name.to_string(),
+                    alias: import_name.node.asname.clone(),
+                    location: stmt.location.into(),
+                    type_checking,
+                });
+            }
+        }
+        ast::StmtKind::ImportFrom {
+            level,
+            module,
+            names,
+        } => {
+            import_froms.push(ImportFrom {
+                module: module.clone().unwrap_or_default(),
+                level: level.unwrap_or(0),
+                names: names
+                    .iter()
+                    .map(|n| ImportedName {
+                        name: n.node.name.to_string(),
+                        alias: n.node.asname.clone(),
+                    })
+                    .collect(),
+                location: stmt.location.into(),
+                type_checking,
+            });
+        }
+        _ => return false,
+    }
+    true
+}
+
+// Find imports anywhere in a block, including inside nested blocks like
+// `if`, `try` and `with`, and with `in_definitions` inside functions and
+// classes too. Imports under `if TYPE_CHECKING:` are marked as such
+fn find_imports_in_body(
+    body: &[ast::Stmt],
+    in_definitions: bool,
+    type_checking: bool,
+    imports: &mut Vec<Import>,
+    import_froms: &mut Vec<ImportFrom>,
+) {
+    for stmt in body {
+        if add_import(stmt, type_checking, imports, import_froms) {
+            continue;
+        }
+        match &stmt.node {
+            ast::StmtKind::FunctionDef { .. }
+            | ast::StmtKind::AsyncFunctionDef { .. }
+            | ast::StmtKind::ClassDef { .. }
+                if !in_definitions => {}
+            ast::StmtKind::If { test, body, orelse } if is_type_checking(test) => {
+                find_imports_in_body(body, in_definitions, true, imports, import_froms);
+                find_imports_in_body(orelse, in_definitions, type_checking, imports, import_froms);
+            }
+            _ => {
+                for block in nested_blocks(stmt) {
+                    find_imports_in_body(
+                        block,
+                        in_definitions,
+                        type_checking,
+                        imports,
+                        import_froms,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// The blocks of statements directly inside a compound statement, like the
+/// body and `else` of an `if`, or the body of a function
+pub(crate) fn nested_blocks(stmt: &ast::Stmt) -> Vec<&[ast::Stmt]> {
+    let mut blocks: Vec<&[ast::Stmt]> = vec![];
+    match &stmt.node {
+        ast::StmtKind::FunctionDef { body, .. }
+        | ast::StmtKind::AsyncFunctionDef { body, .. }
+        | ast::StmtKind::ClassDef { body, .. }
+        | ast::StmtKind::With { body, .. }
+        | ast::StmtKind::AsyncWith { body, .. } => blocks.push(body),
+        ast::StmtKind::If { body, orelse, .. }
+        | ast::StmtKind::For { body, orelse, .. }
+        | ast::StmtKind::AsyncFor { body, orelse, .. }
+        | ast::StmtKind::While { body, orelse, .. } => {
+            blocks.push(body);
+            blocks.push(orelse);
+        }
+        ast::StmtKind::Try {
+            body,
+            handlers,
+            orelse,
+            finalbody,
+        } => {
+            blocks.push(body);
+            for handler in handlers {
+                let ast::ExcepthandlerKind::ExceptHandler { body, .. } = &handler.node;
+                blocks.push(body);
+            }
+            blocks.push(orelse);
+            blocks.push(finalbody);
+        }
+        ast::StmtKind::Match { cases, .. } => {
+            blocks.extend(cases.iter().map(|case| case.body.as_slice()));
+        }
+        _ => {}
+    }
+    blocks
+}
+
+// Whether an `if` tests `TYPE_CHECKING` or `typing.TYPE_CHECKING`
+fn is_type_checking(test: &ast::Expr) -> bool {
+    expr_to_name(test)
+        .is_some_and(|name| name == "TYPE_CHECKING" || name.ends_with(".TYPE_CHECKING"))
+}
+
+// Whether a statement is `if __name__ == "__main__":`, either way around
+fn is_main_block(stmt: &ast::Stmt) -> bool {
+    let ast::StmtKind::If { test, .. } = &stmt.node else {
+        return false;
+    };
+    let ast::ExprKind::Compare {
+        left,
+        ops,
+        comparators,
+    } = &test.node
+    else {
+        return false;
+    };
+    if ops.as_slice() != [ast::Cmpop::Eq] || comparators.len() != 1 {
+        return false;
+    }
+    let is_name =
+        |expr: &ast::Expr| matches!(&expr.node, ast::ExprKind::Name { id, .. } if id == "__name__");
+    let is_main = |expr: &ast::Expr| {
+        matches!(
+            &expr.node,
+            ast::ExprKind::Constant { value: ast::Constant::Str(value), .. } if value == "__main__"
+        )
+    };
+    let right = &comparators[0];
+    (is_name(left) && is_main(right)) || (is_main(left) && is_name(right))
+}
+
+fn line_count(stmt: &ast::Stmt) -> usize {
+    stmt.end_location
+        .map_or(1, |end| end.row() - stmt.location.row() + 1)
+}
+
+// Files and directories to skip, matched relative to the project directory
+pub struct Excludes {
+    project: PathBuf,
+    /// The project directory as an absolute path, if it exists
+    absolute_project: Option<PathBuf>,
+    globs: GlobSet,
+    /// Patterns that start with an absolute path, matched against absolute paths
+    absolute_globs: GlobSet,
+}
+
+impl Excludes {
+    /// Glob patterns relative to the project, or absolute ones like the
+    /// patterns from a config file outside it
+    pub fn new(project: &Path, patterns: &[String]) -> io::Result<Excludes> {
+        let mut builder = GlobSetBuilder::new();
+        let mut absolute_builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            let glob = Glob::new(pattern)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+            if Path::new(pattern).is_absolute() {
+                absolute_builder.add(glob);
+            } else {
+                builder.add(glob);
+            }
+        }
+        let build = |builder: GlobSetBuilder| {
+            builder
+                .build()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))
+        };
+        Ok(Excludes {
+            project: project.to_path_buf(),
+            absolute_project: project.canonicalize().ok(),
+            globs: build(builder)?,
+            absolute_globs: build(absolute_builder)?,
+        })
+    }
+
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        let relative = path
+            .strip_prefix(&self.project)
+            .ok()
+            .or_else(|| path.strip_prefix(self.absolute_project.as_ref()?).ok())
+            .unwrap_or(path);
+        self.globs.is_match(relative)
+            || self
+                .absolute_project
+                .as_ref()
+                .is_some_and(|project| self.absolute_globs.is_match(project.join(relative)))
+    }
+}
+
+// Collect python files under a directory, leaving out any nested source roots
+// since those are walked separately
+fn get_python_paths(
+    dir: &Path,
+    roots: &[SourceRoot],
+    excludes: &Excludes,
+) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    if dir.is_dir() {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if excludes.is_excluded(&path) {
+                continue;
+            }
+            if path.is_dir() {
+                if !roots.iter().any(|root| root.path == path) {
+                    paths.extend(get_python_paths(&path, roots, excludes)?);
+                }
+            } else if let Some(extension) = path.extension() {
+                if extension == "py" {
+                    paths.push(path);
+                }
+            }
+        }
+    }
+    Ok(paths)
+}
+
+fn get_module_name(root: &SourceRoot, path: &Path) -> Option<String> {
+    let relative_path = path.strip_prefix(&root.path).ok()?;
+    let mut parts: Vec<String> = root.package.iter().cloned().collect();
+    for component in relative_path.parent()?.components() {
+        parts.push(component.as_os_str().to_str()?.to_string());
+    }
+    let stem = relative_path.file_stem()?.to_str()?;
+    if stem != "__init__" {
+        parts.push(stem.to_string());
+    }
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join("."))
+    }
+}
+
+pub fn build_module_to_paths(
+    roots: &[SourceRoot],
+    excludes: &Excludes,
+) -> io::Result<BTreeMap<String, PathBuf>> {
+    // build lookup table from python modules to paths. Every root shares one
+    // namespace, and like sys.path the first root to provide a module wins
+    let mut modules_to_paths = BTreeMap::new();
+    for root in roots {
+        let mut python_paths = get_python_paths(&root.path, roots, excludes)?;
+        python_paths.sort();
+        for path in python_paths {
+            if let Some(module_name) = get_module_name(root, &path) {
+                modules_to_paths.entry(module_name).or_insert(path);
+            }
+        }
+    }
+    Ok(modules_to_paths)
+}
+
+/// How to find and parse the files of a project
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    /// Source roots relative to the project. Found from pyproject.toml when empty
+    pub roots: Vec<PathBuf>,
+    /// Glob patterns for files and directories to skip, relative to the project
+    pub exclude: Vec<String>,
+    /// Functions that look up a class by name, like `container.get("TimService")`
+    pub di_patterns: Vec<String>,
+    /// Reuse and update the parse cache in the project's .pydep_cache
+    pub cache: bool,
+}
+
+/// A parsed python project
+#[derive(Debug)]
+pub struct Project {
+    pub path: PathBuf,
+    pub roots: Vec<SourceRoot>,
+    pub modules: BTreeMap<String, PyModule>,
+    pub graph: DependencyGraph,
+}
+
+impl Project {
+    pub fn load(path: &Path, options: &LoadOptions) -> io::Result<Project> {
+        Project::load_with(path, options, |_, _| Ok(()))
+    }
+
+    /// Load the project, calling `parsed` with each module and its position
+    /// in name order as soon as its file is parsed, before the graph is
+    /// built. Files are parsed in parallel, so `parsed` is called from several
+    /// threads in no set order
+    pub fn load_with(
+        path: &Path,
+        options: &LoadOptions,
+        parsed: impl Fn(usize, &PyModule) -> io::Result<()> + Sync,
+    ) -> io::Result<Project> {
+        let roots = find_source_roots(path, &options.roots)?;
+        let excludes = Excludes::new(path, &options.exclude)?;
+        let modules_to_paths = build_module_to_paths(&roots, &excludes)?;
+        // the cache is only an optimization, so without one files just get parsed
+        let cache = if options.cache {
+            let dir = path.join(CACHE_DIR_NAME);
+            ParseCache::new(&dir, &options.di_patterns)
+                .inspect_err(|e| {
+                    eprintln!(
+                        "warning: not caching parsed files, can't use {}: {}",
+                        dir.display(),
+                        e
+                    )
+                })
+                .ok()
+        } else {
+            None
+        };
+
+        // parse files across all cores. Collecting into a sorted map keeps the
+        // output the same no matter what order the files finish in
+        let files: Vec<_> = modules_to_paths.iter().collect();
+        let modules = files
+            .par_iter()
+            .enumerate()
+            .map(|(index, (module_name, path))| {
+                let source_code = fs::read_to_string(path)?;
+                let parse = || parse_module(module_name, &source_code, path, &options.di_patterns);
+                let module = match &cache {
+                    Some(cache) => cache.get_or_parse(module_name, path, &source_code, parse)?,
+                    None => parse()?,
+                };
+                parsed(index, &module)?;
+                Ok((module_name.to_string(), module))
+            })
+            .collect::<io::Result<BTreeMap<_, _>>>()?;
+        if let Some(cache) = &cache {
+            let _ = cache.prune();
+        }
+
+        let graph = DependencyGraph::build(&modules);
+        Ok(Project {
+            path: path.to_path_buf(),
+            roots,
+            modules,
+            graph,
+        })
+    }
+
+    /// The name of the module `module` refers to. It can be a dotted module
+    /// name, or the path of a file or package directory, relative to the
+    /// current directory or the project. Names that match nothing are an
+    /// error that suggests similar module names
+    pub fn resolve_module(&self, module: &str) -> io::Result<String> {
+        if self.modules.contains_key(module) {
+            return Ok(module.to_string());
+        }
+        let not_found = |name: &str| {
+            not_found(
+                "no module named",
+                name,
+                self.modules.keys().map(String::as_str),
+            )
+        };
+
+        let path = Path::new(module);
+        let Some(path) = [path.to_path_buf(), self.path.join(path)]
+            .iter()
+            .find_map(|path| path.canonicalize().ok())
+        else {
+            let name = module.trim_end_matches(".py").replace(['/', '\\'], ".");
+            return Err(not_found(&name));
+        };
+        let mut name = None;
+        for root in &self.roots {
+            let Ok(root_path) = root.path.canonicalize() else {
+                continue;
+            };
+            let root = SourceRoot {
+                path: root_path,
+                package: root.package.clone(),
+            };
+            name = get_module_name(&root, &path);
+            if name
+                .as_ref()
+                .is_some_and(|name| self.modules.contains_key(name))
+            {
+                break;
+            }
+        }
+        match name {
+            Some(name) if self.modules.contains_key(&name) => Ok(name),
+            Some(name) => Err(not_found(&name)),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is not inside any source root", path.display()),
+            )),
+        }
+    }
+
+    /// The fully qualified name of a function, class, or `Class.method` in a
+    /// module. Names the module doesn't define are an error that suggests
+    /// similar names from the module
+    pub fn resolve_function(&self, module_name: &str, function_name: &str) -> io::Result<String> {
+        let fqn = format!("{}::{}", module_name, function_name);
+        if self.graph.find(&fqn).is_some() {
+            return Ok(fqn);
+        }
+        let prefix = format!("{}::", module_name);
+        let names = self
+            .graph
+            .nodes()
+            .iter()
+            .filter(|node| !node.external && node.module == module_name)
+            .filter_map(|node| node.fqn.strip_prefix(&prefix));
+        Err(not_found(
+            &format!("no function in {} named", module_name),
+            function_name,
+            names,
+        ))
+    }
+
+    /// The modules a module imports, and what those import in turn. The
+    /// module can be named as for [`Project::resolve_module`]
+    pub fn module_dependencies(
+        &self,
+        module: &str,
+        options: &TreeOptions,
+    ) -> io::Result<DependencyTree> {
+        let module_name = self.resolve_module(module)?;
+        Ok(self.dependency_tree(&module_name, MODULE_EDGES, options))
+    }
+
+    /// The functions a function calls, and what those call in turn
+    pub fn function_dependencies(
+        &self,
+        module: &str,
+        function_name: &str,
+        options: &TreeOptions,
+    ) -> io::Result<DependencyTree> {
+        let module_name = self.resolve_module(module)?;
+        let fqn = self.resolve_function(&module_name, function_name)?;
+        Ok(self.dependency_tree(&fqn, FUNCTION_EDGES, options))
+    }
+
+    // The tree from a node that's known to be in the graph
+    fn dependency_tree(
+        &self,
+        fqn: &str,
+        kinds: &[EdgeKind],
+        options: &TreeOptions,
+    ) -> DependencyTree {
+        let id = self.graph.find(fqn).unwrap();
+        self.graph.dependency_tree(id, kinds, options)
+    }
+}
+
+/// A NotFound error saying `message name`, suggesting any of `candidates`
+/// that look like `name`
+pub fn not_found<'a>(
+    message: &str,
+    name: &str,
+    candidates: impl Iterator<Item = &'a str>,
+) -> io::Error {
+    let mut message = format!("{} {}", message, name);
+    let suggestions = suggest(name, candidates);
+    if !suggestions.is_empty() {
+        message.push_str(&format!(", did you mean {}?", suggestions.join(", ")));
+    }
+    io::Error::new(io::ErrorKind::NotFound, message)
+}
+
+/// Up to three of `candidates` that look like `name`, best first. The last
+/// parts of dotted names are also compared on their own, so `zoom` suggests
+/// `package.zoom`
+pub fn suggest<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Vec<&'a str> {
+    let last_part = |name: &'a str| name.rsplit(['.', ':']).next().unwrap_or(name);
+    let name_last_part = name.rsplit(['.', ':']).next().unwrap_or(name);
+    let mut scored: Vec<(f64, &str)> = candidates
+        .map(|candidate| {
+            let score = strsim::normalized_damerau_levenshtein(name, candidate)
+                .max(strsim::jaro_winkler(name_last_part, last_part(candidate)));
+            (score, candidate)
+        })
+        .filter(|&(score, _)| score >= 0.8)
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(b.1)));
+    scored
+        .into_iter()
+        .take(3)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn project(dir: &TempDir) -> Project {
+        dir.write("pkg/__init__.py", "");
+        dir.write("pkg/zoom.py", "");
+        dir.write("pkg/sub/__init__.py", "");
+        dir.write("pkg/sub/widget.py", "");
+        Project::load(&dir.path, &LoadOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn modules_by_name_or_path() {
+        let dir = TempDir::new("resolve-module");
+        let project = project(&dir);
+        let resolve = |module: &str| project.resolve_module(module).unwrap();
+
+        assert_eq!(resolve("pkg.zoom"), "pkg.zoom");
+        assert_eq!(resolve("pkg/zoom.py"), "pkg.zoom");
+        assert_eq!(resolve("pkg/sub"), "pkg.sub");
+        assert_eq!(resolve("pkg/sub/widget.py"), "pkg.sub.widget");
+        let absolute = dir.path.join("pkg/sub/widget.py");
+        assert_eq!(resolve(absolute.to_str().unwrap()), "pkg.sub.widget");
+    }
+
+    #[test]
+    fn missing_modules_suggest_similar_names() {
+        let dir = TempDir::new("resolve-missing");
+        let project = project(&dir);
+
+        let error = project.resolve_module("pkg.zom").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        assert_eq!(
+            error.to_string(),
+            "no module named pkg.zom, did you mean pkg.zoom?"
+        );
+        let error = project.resolve_module("pkg/zom.py").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "no module named pkg.zom, did you mean pkg.zoom?"
+        );
+        let error = project.resolve_module("nothing.like.it").unwrap_err();
+        assert_eq!(error.to_string(), "no module named nothing.like.it");
+    }
+
+    #[test]
+    fn suggestions_are_close_and_few() {
+        let candidates = [
+            "app.models",
+            "app.model",
+            "app.modes",
+            "app.modelz",
+            "app.views",
+        ];
+        assert_eq!(
+            suggest("app.modles", candidates.into_iter()),
+            ["app.models", "app.modes", "app.model"]
+        );
+        // app.modelz is close too, but only the best three are kept
+        assert_eq!(
+            suggest("app.modles", candidates[1..].iter().copied()),
+            ["app.modes", "app.model", "app.modelz"]
+        );
+        // the last part of a dotted name is enough
+        assert_eq!(suggest("views", candidates.into_iter()), ["app.views"]);
+        assert!(suggest("database", candidates.into_iter()).is_empty());
+    }
+}
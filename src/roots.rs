@@ -0,0 +1,188 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use toml::Value;
+
+/// A directory that python modules are imported relative to
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceRoot {
+    pub path: PathBuf,
+    /// Package the directory is imported as, when it isn't on the path itself
+    pub package: Option<String>,
+}
+
+impl SourceRoot {
+    fn new(path: PathBuf) -> SourceRoot {
+        SourceRoot {
+            path,
+            package: None,
+        }
+    }
+
+    fn package(path: PathBuf, package: &str) -> SourceRoot {
+        SourceRoot {
+            path,
+            package: Some(package.to_string()),
+        }
+    }
+}
+
+// Roots passed on the command line win, then anything declared in
+// pyproject.toml, and finally the project directory itself
+pub fn find_source_roots(
+    project: &Path,
+    explicit_roots: &[PathBuf],
+) -> io::Result<Vec<SourceRoot>> {
+    if !explicit_roots.is_empty() {
+        return Ok(explicit_roots
+            .iter()
+            .map(|root| SourceRoot::new(project.join(root)))
+            .collect());
+    }
+
+    let pyproject_path = project.join("pyproject.toml");
+    if pyproject_path.is_file() {
+        let source = fs::read_to_string(&pyproject_path)?;
+        let pyproject: Value = toml::from_str(&source).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", pyproject_path.display(), e),
+            )
+        })?;
+        let roots = read_pyproject_roots(project, &pyproject);
+        if !roots.is_empty() {
+            return Ok(roots);
+        }
+    }
+
+    Ok(vec![SourceRoot::new(project.to_path_buf())])
+}
+
+// Collect the package directories declared for setuptools or poetry
+fn read_pyproject_roots(project: &Path, pyproject: &Value) -> Vec<SourceRoot> {
+    let mut roots = vec![];
+    let mut add_root = |root: SourceRoot| {
+        if !roots.contains(&root) {
+            roots.push(root);
+        }
+    };
+
+    let setuptools = pyproject
+        .get("tool")
+        .and_then(|tool| tool.get("setuptools"));
+
+    // [tool.setuptools.package-dir] maps package names to directories, with ""
+    // standing for the directory all top level packages live in
+    if let Some(package_dir) = setuptools
+        .and_then(|setuptools| setuptools.get("package-dir"))
+        .and_then(Value::as_table)
+    {
+        for (package, dir) in package_dir {
+            if let Some(dir) = dir.as_str() {
+                if package.is_empty() {
+                    add_root(SourceRoot::new(project.join(dir)));
+                } else {
+                    add_root(SourceRoot::package(project.join(dir), package));
+                }
+            }
+        }
+    }
+
+    // [tool.setuptools.packages.find] where = ["src"]
+    if let Some(wheres) = setuptools
+        .and_then(|setuptools| setuptools.get("packages"))
+        .and_then(|packages| packages.get("find"))
+        .and_then(|find| find.get("where"))
+        .and_then(Value::as_array)
+    {
+        for dir in wheres.iter().filter_map(Value::as_str) {
+            add_root(SourceRoot::new(project.join(dir)));
+        }
+    }
+
+    // [tool.poetry] packages = [{ include = "mypkg", from = "src" }]
+    if let Some(packages) = pyproject
+        .get("tool")
+        .and_then(|tool| tool.get("poetry"))
+        .and_then(|poetry| poetry.get("packages"))
+        .and_then(Value::as_array)
+    {
+        for package in packages {
+            let from = package.get("from").and_then(Value::as_str).unwrap_or(".");
+            add_root(SourceRoot::new(project.join(from)));
+        }
+    }
+
+    roots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roots(pyproject: &str) -> Vec<SourceRoot> {
+        let pyproject: Value = toml::from_str(pyproject).unwrap();
+        read_pyproject_roots(Path::new("proj"), &pyproject)
+    }
+
+    #[test]
+    fn setuptools_package_dir() {
+        let found = roots(
+            r#"
+[tool.setuptools.package-dir]
+"" = "src"
+mypkg = "lib/mypkg"
+"#,
+        );
+        assert_eq!(
+            found,
+            [
+                SourceRoot::new(PathBuf::from("proj/src")),
+                SourceRoot::package(PathBuf::from("proj/lib/mypkg"), "mypkg"),
+            ]
+        );
+    }
+
+    #[test]
+    fn setuptools_packages_find() {
+        let found = roots(
+            r#"
+[tool.setuptools.packages.find]
+where = ["src", "plugins"]
+"#,
+        );
+        assert_eq!(
+            found,
+            [
+                SourceRoot::new(PathBuf::from("proj/src")),
+                SourceRoot::new(PathBuf::from("proj/plugins")),
+            ]
+        );
+    }
+
+    #[test]
+    fn poetry_packages() {
+        let found = roots(
+            r#"
+[tool.poetry]
+packages = [{ include = "mypkg", from = "src" }, { include = "other", from = "src" }, { include = "top" }]
+"#,
+        );
+        assert_eq!(
+            found,
+            [
+                SourceRoot::new(PathBuf::from("proj/src")),
+                SourceRoot::new(PathBuf::from("proj/.")),
+            ]
+        );
+    }
+
+    #[test]
+    fn project_dir_without_declared_roots() {
+        assert!(roots("[tool.black]\nline-length = 100\n").is_empty());
+        let found = find_source_roots(Path::new("/nonexistent/proj"), &[]).unwrap();
+        assert_eq!(found, [SourceRoot::new(PathBuf::from("/nonexistent/proj"))]);
+    }
+}
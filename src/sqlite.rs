@@ -0,0 +1,233 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use rusqlite::{params, Connection};
+
+use crate::graph::{DependencyGraph, EdgeKind, NodeId, NodeKind};
+
+const SCHEMA: &str = "
+CREATE TABLE files (
+    id INTEGER PRIMARY KEY,
+    path TEXT NOT NULL UNIQUE
+);
+CREATE TABLE modules (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    package TEXT,
+    file_id INTEGER REFERENCES files(id),
+    external INTEGER NOT NULL
+);
+CREATE TABLE classes (
+    id INTEGER PRIMARY KEY,
+    fqn TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL,
+    module_id INTEGER NOT NULL REFERENCES modules(id),
+    line INTEGER,
+    column INTEGER
+);
+CREATE TABLE functions (
+    id INTEGER PRIMARY KEY,
+    fqn TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    module_id INTEGER NOT NULL REFERENCES modules(id),
+    class_id INTEGER REFERENCES classes(id),
+    line INTEGER,
+    column INTEGER
+);
+CREATE TABLE imports (
+    id INTEGER PRIMARY KEY,
+    module_id INTEGER NOT NULL REFERENCES modules(id),
+    imported_module_id INTEGER NOT NULL REFERENCES modules(id),
+    file_id INTEGER REFERENCES files(id),
+    line INTEGER NOT NULL,
+    column INTEGER NOT NULL
+);
+CREATE TABLE calls (
+    id INTEGER PRIMARY KEY,
+    caller TEXT NOT NULL,
+    callee TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    caller_module_id INTEGER NOT NULL REFERENCES modules(id),
+    callee_module TEXT NOT NULL,
+    file_id INTEGER REFERENCES files(id),
+    line INTEGER NOT NULL,
+    column INTEGER NOT NULL
+);
+CREATE INDEX imports_module ON imports(module_id);
+CREATE INDEX imports_imported_module ON imports(imported_module_id);
+CREATE INDEX calls_caller ON calls(caller);
+CREATE INDEX calls_callee ON calls(callee);
+";
+
+/// Write the graph to a new SQLite database, replacing the file if it exists
+pub fn export_sqlite(path: &Path, graph: &DependencyGraph) -> io::Result<()> {
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    write_database(path, graph).map_err(io::Error::other)
+}
+
+fn write_database(path: &Path, graph: &DependencyGraph) -> rusqlite::Result<()> {
+    let mut connection = Connection::open(path)?;
+    let transaction = connection.transaction()?;
+    transaction.execute_batch(SCHEMA)?;
+
+    let mut file_ids: HashMap<&PathBuf, i64> = HashMap::new();
+    let mut module_ids: HashMap<&str, i64> = HashMap::new();
+    let mut class_ids: HashMap<&str, i64> = HashMap::new();
+    let file_id = |file_ids: &HashMap<&PathBuf, i64>, id: NodeId| {
+        graph
+            .node(id)
+            .path
+            .as_ref()
+            .and_then(|path| file_ids.get(path).copied())
+    };
+
+    for node in graph.nodes() {
+        if let Some(path) = &node.path {
+            if !file_ids.contains_key(path) {
+                transaction.execute(
+                    "INSERT INTO files (path) VALUES (?1)",
+                    params![path.to_string_lossy()],
+                )?;
+                file_ids.insert(path, transaction.last_insert_rowid());
+            }
+        }
+    }
+
+    for node in graph
+        .nodes()
+        .iter()
+        .filter(|node| node.kind == NodeKind::Module)
+    {
+        transaction.execute(
+            "INSERT INTO modules (name, package, file_id, external) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                node.fqn,
+                node.package(),
+                node.path.as_ref().and_then(|path| file_ids.get(path)),
+                node.external
+            ],
+        )?;
+        module_ids.insert(&node.fqn, transaction.last_insert_rowid());
+    }
+
+    for node in graph
+        .nodes()
+        .iter()
+        .filter(|node| node.kind == NodeKind::Class)
+    {
+        let (_, name) = node.fqn.split_once("::").unwrap_or(("", &node.fqn));
+        transaction.execute(
+            "INSERT INTO classes (fqn, name, module_id, line, column) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                node.fqn,
+                name,
+                module_ids[node.module.as_str()],
+                node.location.map(|location| location.line),
+                node.location.map(|location| location.column)
+            ],
+        )?;
+        class_ids.insert(&node.fqn, transaction.last_insert_rowid());
+    }
+
+    for node in graph.nodes() {
+        if !matches!(node.kind, NodeKind::Function | NodeKind::Method) {
+            continue;
+        }
+        let (_, qualified_name) = node.fqn.split_once("::").unwrap_or(("", &node.fqn));
+        let (class_id, name) = match qualified_name.rsplit_once('.') {
+            Some((class, name)) => (
+                class_ids
+                    .get(format!("{}::{}", node.module, class).as_str())
+                    .copied(),
+                name,
+            ),
+            None => (None, qualified_name),
+        };
+        transaction.execute(
+            "INSERT INTO functions (fqn, name, kind, module_id, class_id, line, column)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                node.fqn,
+                name,
+                node.kind.as_str(),
+                module_ids[node.module.as_str()],
+                class_id,
+                node.location.map(|location| location.line),
+                node.location.map(|location| location.column)
+            ],
+        )?;
+    }
+
+    for edge in graph.edges() {
+        let from = graph.node(edge.from);
+        let to = graph.node(edge.to);
+        match edge.kind {
+            EdgeKind::Import => {
+                transaction.execute(
+                    "INSERT INTO imports (module_id, imported_module_id, file_id, line, column)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        module_ids[from.module.as_str()],
+                        module_ids[to.fqn.as_str()],
+                        file_id(&file_ids, edge.from),
+                        edge.location.line,
+                        edge.location.column
+                    ],
+                )?;
+            }
+            EdgeKind::Call | EdgeKind::Instantiate => {
+                transaction.execute(
+                    "INSERT INTO calls
+                     (caller, callee, kind, caller_module_id, callee_module, file_id, line, column)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    params![
+                        from.fqn,
+                        to.fqn,
+                        edge.kind.as_str(),
+                        module_ids[from.module.as_str()],
+                        to.module,
+                        file_id(&file_ids, edge.from),
+                        edge.location.line,
+                        edge.location.column
+                    ],
+                )?;
+            }
+            _ => {}
+        }
+    }
+
+    transaction.commit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{build_graph, TempDir, REPEATED_NAMES};
+
+    #[test]
+    fn repeated_names_are_one_function() {
+        let graph = build_graph(&[("pkg/m.py", REPEATED_NAMES)]);
+        let dir = TempDir::new("sqlite-repeated");
+        let path = dir.path.join("pydep.db");
+
+        export_sqlite(&path, &graph).unwrap();
+        let connection = Connection::open(&path).unwrap();
+        let count = |fqn: &str| -> i64 {
+            connection
+                .query_row(
+                    "SELECT count(*) FROM functions WHERE fqn = ?1",
+                    [fqn],
+                    |row| row.get(0),
+                )
+                .unwrap()
+        };
+        assert_eq!(count("pkg.m::C.x"), 1);
+        assert_eq!(count("pkg.m::f"), 1);
+    }
+}
@@ -0,0 +1,98 @@
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+};
+
+use crate::{
+    graph::{DependencyGraph, EdgeKind, NodeKind, MODULE_EDGES},
+    tree::{write_transitive_deps, TreeOptions},
+};
+
+/// Write the direct imports of every module in the project, the modules
+/// nothing imports, and how big the project is
+pub fn write_project_summary(out: &mut impl Write, graph: &DependencyGraph) -> io::Result<()> {
+    let modules: Vec<usize> = (0..graph.nodes().len())
+        .filter(|&id| {
+            let node = graph.node(id);
+            node.kind == NodeKind::Module && !node.external
+        })
+        .collect();
+
+    let direct = TreeOptions {
+        max_depth: Some(1),
+        elide_repeats: false,
+    };
+    for &id in &modules {
+        write_transitive_deps(out, &graph.dependency_tree(id, MODULE_EDGES, &direct))?;
+    }
+
+    writeln!(out)?;
+    writeln!(out, "entry points (modules nothing imports):")?;
+    let entry_points = entry_points(graph);
+    if entry_points.is_empty() {
+        writeln!(out, "  none")?;
+    }
+    for module in entry_points {
+        writeln!(out, "  {}", module)?;
+    }
+
+    let count = |kind: NodeKind, external: bool| {
+        graph
+            .nodes()
+            .iter()
+            .filter(|node| node.kind == kind && node.external == external)
+            .count()
+    };
+    let edges = |kind: EdgeKind| {
+        graph
+            .edges()
+            .iter()
+            .filter(|edge| edge.kind == kind)
+            .count()
+    };
+    writeln!(out)?;
+    writeln!(out, "totals:")?;
+    for (name, total) in [
+        ("modules", modules.len()),
+        ("classes", count(NodeKind::Class, false)),
+        ("functions", count(NodeKind::Function, false)),
+        ("methods", count(NodeKind::Method, false)),
+        ("external modules", count(NodeKind::Module, true)),
+        ("imports", edges(EdgeKind::Import)),
+        (
+            "calls",
+            edges(EdgeKind::Call) + edges(EdgeKind::Instantiate),
+        ),
+    ] {
+        writeln!(out, "  {}: {}", name, total)?;
+    }
+    Ok(())
+}
+
+/// The project's modules that no other module imports, sorted by name.
+/// Importing a module also imports the packages it is in, so a package only
+/// counts if none of its modules are imported either
+pub fn entry_points(graph: &DependencyGraph) -> Vec<&str> {
+    let mut imported = HashSet::new();
+    for edge in graph.edges() {
+        let from = &graph.node(edge.from).module;
+        let to = &graph.node(edge.to).fqn;
+        if edge.kind != EdgeKind::Import || from == to {
+            continue;
+        }
+        imported.insert(to.as_str());
+        for (index, _) in to.match_indices('.') {
+            imported.insert(&to[..index]);
+        }
+    }
+
+    let mut entry_points: Vec<&str> = graph
+        .nodes()
+        .iter()
+        .filter(|node| node.kind == NodeKind::Module && !node.external)
+        .map(|node| node.fqn.as_str())
+        .filter(|name| !imported.contains(name))
+        .collect();
+    entry_points.sort_unstable();
+    entry_points
+}
@@ -0,0 +1,101 @@
+//! Fixtures shared by the unit tests
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use crate::{
+    graph::DependencyGraph,
+    project::{parse_module, Project, PyModule},
+};
+
+/// Source that defines names twice: a property with its setter, and the
+/// variants of a `typing.overload`
+pub const REPEATED_NAMES: &str = "\
+from typing import overload
+
+class C:
+    @property
+    def x(self):
+        return 1
+
+    @x.setter
+    def x(self, value):
+        pass
+
+@overload
+def f(a: int) -> int: ...
+@overload
+def f(a: str) -> str: ...
+def f(a):
+    return a
+";
+
+/// Parse a module from its path in the project, like `pkg/m.py` or
+/// `pkg/__init__.py`
+pub fn parse(path: &str, source: &str) -> PyModule {
+    parse_module(&module_name(path), source, Path::new(path), &[]).unwrap()
+}
+
+/// The modules of a project given as `(path, source)` pairs, by name
+pub fn modules(files: &[(&str, &str)]) -> BTreeMap<String, PyModule> {
+    files
+        .iter()
+        .map(|(path, source)| (module_name(path), parse(path, source)))
+        .collect()
+}
+
+/// The graph of a project given as `(path, source)` pairs
+pub fn build_graph(files: &[(&str, &str)]) -> DependencyGraph {
+    DependencyGraph::build(&modules(files))
+}
+
+/// A project given as `(path, source)` pairs, with no files on disk
+pub fn project(files: &[(&str, &str)]) -> Project {
+    let modules = modules(files);
+    Project {
+        path: PathBuf::new(),
+        roots: vec![],
+        graph: DependencyGraph::build(&modules),
+        modules,
+    }
+}
+
+fn module_name(path: &str) -> String {
+    let path = path.trim_end_matches(".py");
+    let path = path.strip_suffix("/__init__").unwrap_or(path);
+    path.replace('/', ".")
+}
+
+/// A directory of its own under the system temp dir, removed when dropped
+pub struct TempDir {
+    pub path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new(name: &str) -> TempDir {
+        let path = std::env::temp_dir().join(format!("pydep-test-{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir {
+            path: path.canonicalize().unwrap(),
+        }
+    }
+
+    /// Write a file under the directory, creating its parents
+    pub fn write(&self, path: &str, contents: &str) -> PathBuf {
+        let path = self.path.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
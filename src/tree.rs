@@ -1,138 +1,147 @@
 use std::{
-    collections::{HashMap, HashSet},
-    io,
+    collections::HashSet,
+    io::{self, Write},
 };
 
 use ptree::TreeBuilder;
+use serde::Serialize;
 
-use crate::PyModule;
+/// A module or function, along with everything it depends on
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DependencyTree {
+    pub name: String,
+    pub dependencies: Vec<DependencyTree>,
+    /// Listed in full earlier in the tree, so its dependencies are left out
+    pub repeated: bool,
+}
 
-pub fn print_transitive_deps(
-    modules: &HashMap<String, PyModule>,
-    module_name: &str,
-    function_name: Option<&str>,
-) -> io::Result<()> {
-    // Build a tree representing the transitive dependencies of the module
-    let mut tree_builder;
-    match function_name {
-        Some(function_name) => {
-            tree_builder = TreeBuilder::new(format!("{}::{}", module_name, function_name));
-            add_function_dependencies_to_tree(
-                &mut tree_builder,
-                modules,
-                module_name,
-                function_name,
-            )
-        }
-        None => {
-            tree_builder = TreeBuilder::new(module_name.to_string());
-            add_module_dependencies_to_tree(&mut tree_builder, modules, module_name)
+impl DependencyTree {
+    pub(crate) fn new(name: String) -> DependencyTree {
+        DependencyTree {
+            name,
+            dependencies: vec![],
+            repeated: false,
         }
     }
 
-    // Print the tree
-    ptree::print_tree(&tree_builder.build())?;
-    Ok(())
-}
-
-// Recursively add the dependencies of a module to a tree
-pub fn add_function_dependencies_to_tree(
-    tree_builder: &mut TreeBuilder,
-    modules: &HashMap<String, PyModule>,
-    module_name: &str,
-    function_name: &str,
-) {
-    if let Some(PyModule {
-        imports,
-        import_froms,
-        functions,
-        ..
-    }) = modules.get(module_name)
-    {
-        // build reverse map of imported names to imported modules
-        let mut rev_imports = HashMap::new();
-        for (module, names) in import_froms {
-            for name in names {
-                rev_imports.insert(name, module);
+    /// How many different nodes the tree has below its root
+    pub fn unique_dependencies(&self) -> usize {
+        let mut names = HashSet::new();
+        let mut stack: Vec<&DependencyTree> = self.dependencies.iter().collect();
+        while let Some(tree) = stack.pop() {
+            if tree.name != self.name {
+                names.insert(tree.name.as_str());
             }
+            stack.extend(&tree.dependencies);
         }
+        names.len()
+    }
+}
 
-        let mut module_imports = HashSet::new();
-        for module in imports {
-            module_imports.insert(module);
-        }
-
-        if let Some((_, calls)) = functions
-            .iter()
-            .find(|(function, _)| function == function_name)
-        {
-            // Add the dependencies of the function to the tree
-            for call in calls {
-                // Check if the call references an import
-                if let Some(module) = rev_imports.get(call) {
-                    // Add the dependency to the tree
-                    let child_builder = tree_builder.begin_child(format!("{}::{}", module, call));
+/// Limits on how much of a dependency tree to build
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TreeOptions {
+    /// How many levels below the root to go
+    pub max_depth: Option<usize>,
+    /// List each node's dependencies only the first time it appears, and
+    /// mark it as repeated everywhere after that
+    pub elide_repeats: bool,
+}
 
-                    // Recursively add the dependencies of the dependency to the tree
-                    add_function_dependencies_to_tree(child_builder, modules, module, call);
+pub fn write_transitive_deps(out: &mut impl Write, tree: &DependencyTree) -> io::Result<()> {
+    let mut tree_builder = TreeBuilder::new(tree.name.to_string());
+    add_dependencies_to_tree(&mut tree_builder, &tree.dependencies);
+    ptree::write_tree(&tree_builder.build(), out)
+}
 
-                    tree_builder.end_child();
-                } else if functions.iter().any(|(function, _)| function == call) {
-                    // Check if the call references a function in the same module
-                    // Add the dependency to the tree
-                    let child_builder =
-                        tree_builder.begin_child(format!("{}::{}", module_name, call));
+fn add_dependencies_to_tree(tree_builder: &mut TreeBuilder, dependencies: &[DependencyTree]) {
+    for dependency in dependencies {
+        let name = if dependency.repeated {
+            format!("{} (see above)", dependency.name)
+        } else {
+            dependency.name.to_string()
+        };
+        let child_builder = tree_builder.begin_child(name);
+        add_dependencies_to_tree(child_builder, &dependency.dependencies);
+        tree_builder.end_child();
+    }
+}
 
-                    // Recursively add the dependencies of the dependency to the tree
-                    add_function_dependencies_to_tree(child_builder, modules, module_name, call);
-                } else {
-                    // Check if the call references a module
-                    // Split the call into parts
-                    let split: Vec<_> = call.split(".").collect();
-                    if split.len() > 1 {
-                        let function = split.last().unwrap();
-                        let module = split[..split.len() - 1].join(".");
-                        if module_imports.contains(&module) {
-                            // Add the dependency to the tree
-                            let child_builder =
-                                tree_builder.begin_child(format!("{}::{}", module, function));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        graph::{DependencyGraph, MODULE_EDGES},
+        testing::build_graph,
+    };
 
-                            // Recursively add the dependencies of the dependency to the tree
-                            add_function_dependencies_to_tree(
-                                child_builder,
-                                modules,
-                                &module,
-                                function,
-                            );
+    // a imports b and c, which both import d, which imports e
+    fn diamond() -> DependencyGraph {
+        build_graph(&[
+            ("a.py", "import b\nimport c\n"),
+            ("b.py", "import d\n"),
+            ("c.py", "import d\n"),
+            ("d.py", "import e\n"),
+            ("e.py", ""),
+        ])
+    }
 
-                            tree_builder.end_child();
-                        }
-                    }
-                }
-            }
-        }
+    fn tree_text(graph: &DependencyGraph, root: &str, options: TreeOptions) -> (String, usize) {
+        let root = graph.find(root).unwrap();
+        let tree = graph.dependency_tree(root, MODULE_EDGES, &options);
+        let mut out = Vec::new();
+        write_transitive_deps(&mut out, &tree).unwrap();
+        (String::from_utf8(out).unwrap(), tree.unique_dependencies())
     }
-}
 
-pub fn add_module_dependencies_to_tree(
-    tree_builder: &mut TreeBuilder,
-    modules: &HashMap<String, PyModule>,
-    module_name: &str,
-) {
-    if let Some(PyModule {
-        imports,
-        import_froms,
-        ..
-    }) = modules.get(module_name)
-    {
-        for module in import_froms.iter().map(|(m, _)| m).chain(imports.iter()) {
-            // Add the dependency to the tree
-            let child_builder = tree_builder.begin_child(module.to_string());
+    #[test]
+    fn repeats_are_listed_once() {
+        let options = TreeOptions {
+            max_depth: None,
+            elide_repeats: true,
+        };
+        let (text, unique) = tree_text(&diamond(), "a", options);
+        assert_eq!(
+            text,
+            "a\n├─ b\n│  └─ d\n│     └─ e\n└─ c\n   └─ d (see above)\n"
+        );
+        assert_eq!(unique, 4);
 
-            // Recursively add the dependencies of the dependency to the tree
-            add_module_dependencies_to_tree(child_builder, modules, module);
+        let (text, unique) = tree_text(&diamond(), "a", TreeOptions::default());
+        assert_eq!(
+            text,
+            "a\n├─ b\n│  └─ d\n│     └─ e\n└─ c\n   └─ d\n      └─ e\n"
+        );
+        assert_eq!(unique, 4);
+    }
 
-            tree_builder.end_child();
-        }
+    #[test]
+    fn depth_cuts_off_the_tree() {
+        let options = TreeOptions {
+            max_depth: Some(2),
+            elide_repeats: true,
+        };
+        let (text, unique) = tree_text(&diamond(), "a", options);
+        assert_eq!(text, "a\n├─ b\n│  └─ d\n└─ c\n   └─ d (see above)\n");
+        assert_eq!(unique, 3);
+    }
+
+    #[test]
+    fn repeats_cut_off_deeper_down_are_listed_again() {
+        // d is first reached below b, where the depth limit leaves out e, so
+        // it is listed in full again where a imports it directly
+        let graph = build_graph(&[
+            ("a.py", "import b\nimport d\n"),
+            ("b.py", "import d\n"),
+            ("d.py", "import e\n"),
+            ("e.py", ""),
+        ]);
+        let options = TreeOptions {
+            max_depth: Some(2),
+            elide_repeats: true,
+        };
+        let (text, unique) = tree_text(&graph, "a", options);
+        assert_eq!(text, "a\n├─ b\n│  └─ d\n└─ d\n   └─ e\n");
+        assert_eq!(unique, 3);
     }
 }
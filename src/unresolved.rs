@@ -0,0 +1,88 @@
+use std::io::{self, Write};
+
+use crate::{
+    graph::{DependencyGraph, EdgeKind, Node, UnresolvedReason},
+    project::Location,
+    table::Table,
+};
+
+/// Write every call that doesn't lead to project code, with a guess at why,
+/// and how many calls were resolved. Only calls made by nodes that
+/// `in_scope` accepts are counted
+pub fn write_unresolved(
+    out: &mut impl Write,
+    graph: &DependencyGraph,
+    in_scope: impl Fn(&Node) -> bool,
+) -> io::Result<()> {
+    // (caller, where, name called, reason)
+    let mut calls: Vec<(&Node, Location, &str, &str)> = vec![];
+    let mut resolved = 0;
+    let mut external = 0;
+    for edge in graph.edges() {
+        let from = graph.node(edge.from);
+        if !matches!(edge.kind, EdgeKind::Call | EdgeKind::Instantiate) || !in_scope(from) {
+            continue;
+        }
+        let to = graph.node(edge.to);
+        if to.external {
+            external += 1;
+            calls.push((from, edge.location, &to.fqn, "external module"));
+        } else {
+            resolved += 1;
+        }
+    }
+    let (mut builtin, mut dynamic, mut unknown) = (0, 0, 0);
+    for call in graph.unresolved_calls() {
+        let from = graph.node(call.from);
+        if !in_scope(from) {
+            continue;
+        }
+        *match call.reason {
+            UnresolvedReason::Builtin => &mut builtin,
+            UnresolvedReason::DynamicAttribute => &mut dynamic,
+            UnresolvedReason::UnknownLocal => &mut unknown,
+        } += 1;
+        calls.push((from, call.location, &call.name, call.reason.as_str()));
+    }
+    calls.sort_by(|a, b| (&a.0.path, a.1).cmp(&(&b.0.path, b.1)));
+
+    let mut table = Table::new(&["location", "call", "reason", "caller"]);
+    for (from, location, name, reason) in calls {
+        let path = from
+            .path
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_default();
+        table.push(vec![
+            format!("{}:{}:{}", path, location.line, location.column),
+            name.to_string(),
+            reason.to_string(),
+            from.fqn.to_string(),
+        ]);
+    }
+    table.write_text(out)?;
+
+    let total = resolved + external + builtin + dynamic + unknown;
+    writeln!(out)?;
+    writeln!(out, "calls: {}", total)?;
+    for (name, count) in [
+        ("resolved in the project", resolved),
+        ("external module", external),
+        ("builtin", builtin),
+        ("dynamic attribute", dynamic),
+        ("unknown local", unknown),
+    ] {
+        writeln!(out, "  {}: {}", name, count)?;
+    }
+    // builtins and other packages are never project code, so they don't
+    // count against resolution
+    let resolvable = resolved + dynamic + unknown;
+    if resolvable > 0 {
+        writeln!(
+            out,
+            "resolution rate: {:.1}% of calls that aren't builtins or external",
+            100.0 * resolved as f64 / resolvable as f64
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,446 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use rustpython_parser::{ast, parser::parse_program};
+
+use crate::{
+    project::{nested_blocks, Location, PyModule},
+    table::Table,
+};
+
+/// A name imported outside any function or class that the module never uses
+#[derive(Debug, Clone)]
+pub struct UnusedImport {
+    pub path: PathBuf,
+    /// Where the import statement starts
+    pub location: Location,
+    /// The imported module or name
+    pub name: String,
+    pub alias: Option<String>,
+}
+
+/// Find the module's imports outside functions and classes that bind a name
+/// nothing in the module reads. Names listed in `__all__` and imports like `import x as x` are
+/// re-exports, and so is everything a package's __init__.py imports when it
+/// doesn't set `__all__`
+pub fn find_unused_imports(module: &PyModule) -> Vec<UnusedImport> {
+    if module.is_package() && module.exports.is_none() {
+        return vec![];
+    }
+    let is_used = |bound: &str| {
+        module.names.contains(bound)
+            || module
+                .exports
+                .as_ref()
+                .is_some_and(|exports| exports.iter().any(|name| name == bound))
+    };
+    let unused = |name: &str, alias: &Option<String>, location: Location| UnusedImport {
+        path: module.path.clone(),
+        location,
+        name: name.to_string(),
+        alias: alias.clone(),
+    };
+
+    let mut found = vec![];
+    for import in &module.imports {
+        // `import a.b` binds `a`
+        let bound = match &import.alias {
+            Some(alias) if alias == &import.module => continue,
+            Some(alias) => alias.as_str(),
+            None => import.module.split('.').next().unwrap_or_default(),
+        };
+        if !is_used(bound) {
+            found.push(unused(&import.module, &import.alias, import.location));
+        }
+    }
+    for import_from in &module.import_froms {
+        if import_from.module == "__future__" && import_from.level == 0 {
+            continue;
+        }
+        for name in &import_from.names {
+            let is_reexport = name.alias.as_ref() == Some(&name.name);
+            if name.name == "*" || is_reexport || is_used(name.bound_name()) {
+                continue;
+            }
+            found.push(unused(&name.name, &name.alias, import_from.location));
+        }
+    }
+    found.sort_by_key(|unused| unused.location);
+    found
+}
+
+/// Rewrite the import statements in a module's file to drop the unused names,
+/// removing statements that import nothing else. A block left empty gets a
+/// `pass`. Statements that share a line with other code are left alone.
+/// Returns the imports that were removed
+pub fn remove_unused_imports(
+    module: &PyModule,
+    unused: &[UnusedImport],
+) -> io::Result<Vec<UnusedImport>> {
+    let path = &module.path;
+    let source = fs::read_to_string(path)?;
+    let ast = parse_program(&source, &path.to_string_lossy()).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), e),
+        )
+    })?;
+    let mut lines: Vec<&str> = source.split_inclusive('\n').collect();
+
+    let mut by_statement: HashMap<Location, Vec<&UnusedImport>> = HashMap::new();
+    for import in unused {
+        by_statement
+            .entry(import.location)
+            .or_default()
+            .push(import);
+    }
+
+    let mut replacements = vec![];
+    plan_block(&ast, true, &lines, &by_statement, &mut replacements);
+    let removed: Vec<UnusedImport> = replacements
+        .iter()
+        .flat_map(|replacement| replacement.removed.iter().map(|&import| import.clone()))
+        .collect();
+    if replacements.is_empty() {
+        return Ok(removed);
+    }
+    // replace from the bottom up so earlier line numbers stay valid
+    replacements.sort_by_key(|replacement| replacement.first);
+    for replacement in replacements.iter().rev() {
+        let lines_replaced = replacement.first..replacement.first + replacement.count;
+        lines.splice(lines_replaced, [replacement.text.as_str()]);
+    }
+    fs::write(path, lines.concat())?;
+    Ok(removed)
+}
+
+// New text for the lines of an import statement
+struct Replacement<'a> {
+    first: usize,
+    count: usize,
+    /// Empty when the statement imports nothing that is used
+    text: String,
+    indent: &'a str,
+    newline: &'static str,
+    removed: Vec<&'a UnusedImport>,
+}
+
+// Plan the rewrites of the statements in a block, and in the blocks nested in
+// it outside functions and classes. When every statement in a nested block
+// goes, the first is replaced by `pass`
+fn plan_block<'a>(
+    block: &[ast::Stmt],
+    top_level: bool,
+    lines: &[&'a str],
+    by_statement: &HashMap<Location, Vec<&'a UnusedImport>>,
+    replacements: &mut Vec<Replacement<'a>>,
+) {
+    let first_replacement = replacements.len();
+    let mut emptied = 0;
+    for stmt in block {
+        if let Some(unused) = by_statement.get(&Location::from(stmt.location)) {
+            if let Some(replacement) = rewrite_import(stmt, unused, lines) {
+                if replacement.text.is_empty() {
+                    emptied += 1;
+                }
+                replacements.push(replacement);
+            }
+            continue;
+        }
+        if !matches!(
+            stmt.node,
+            ast::StmtKind::FunctionDef { .. }
+                | ast::StmtKind::AsyncFunctionDef { .. }
+                | ast::StmtKind::ClassDef { .. }
+        ) {
+            for nested in nested_blocks(stmt) {
+                plan_block(nested, false, lines, by_statement, replacements);
+            }
+        }
+    }
+    if !top_level && !block.is_empty() && emptied == block.len() {
+        let first = &mut replacements[first_replacement];
+        first.text = format!("{}pass{}", first.indent, first.newline);
+    }
+}
+
+// The import statement without the unused names, or None if it shares a
+// line with other code
+fn rewrite_import<'a>(
+    stmt: &ast::Stmt,
+    unused: &[&'a UnusedImport],
+    lines: &[&'a str],
+) -> Option<Replacement<'a>> {
+    let end = stmt.end_location?;
+    let (first, last) = (stmt.location.row() - 1, end.row() - 1);
+    let indent = lines[first].get(..stmt.location.column())?;
+    let rest = lines[last].get(end.column()..)?.trim();
+    if !indent.trim().is_empty() || !(rest.is_empty() || rest.starts_with('#')) {
+        return None;
+    }
+    let is_unused = |alias: &ast::Alias| {
+        unused
+            .iter()
+            .any(|import| import.name == alias.node.name && import.alias == alias.node.asname)
+    };
+    let (names, prefix) = match &stmt.node {
+        ast::StmtKind::Import { names } => (names, format!("{}import ", indent)),
+        ast::StmtKind::ImportFrom {
+            module,
+            names,
+            level,
+        } => (
+            names,
+            format!(
+                "{}from {}{} import ",
+                indent,
+                ".".repeat(level.unwrap_or(0)),
+                module.as_deref().unwrap_or_default()
+            ),
+        ),
+        _ => return None,
+    };
+    let kept: Vec<String> = names
+        .iter()
+        .filter(|alias| !is_unused(alias))
+        .map(alias_source)
+        .collect();
+
+    let newline = if lines[last].ends_with("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let text = if kept.is_empty() {
+        String::new()
+    } else if first == last {
+        let comment = if rest.is_empty() {
+            String::new()
+        } else {
+            format!("  {}", rest)
+        };
+        format!("{}{}{}{}", prefix, kept.join(", "), comment, newline)
+    } else if let Some(text) = remove_names_by_line(names, is_unused, lines, first, last) {
+        text
+    } else if matches!(stmt.node, ast::StmtKind::ImportFrom { .. }) {
+        let mut text = format!("{}({}", prefix, newline);
+        for name in &kept {
+            text.push_str(&format!("{}    {},{}", indent, name, newline));
+        }
+        text.push_str(&format!("{}){}", indent, newline));
+        text
+    } else {
+        format!("{}{}{}", prefix, kept.join(", "), newline)
+    };
+    Some(Replacement {
+        first,
+        count: last - first + 1,
+        text,
+        indent,
+        newline,
+        removed: unused.to_vec(),
+    })
+}
+
+// How an imported name is written, like `a` or `a as b`
+fn alias_source(alias: &ast::Alias) -> String {
+    match &alias.node.asname {
+        Some(asname) => format!("{} as {}", alias.node.name, asname),
+        None => alias.node.name.to_string(),
+    }
+}
+
+// Remove names from an import written across several lines one line at a
+// time, so the names that stay keep their lines and comments, and lines left
+// with no names go. None when there are names to remove on the first or last
+// line, which also hold the rest of the statement
+fn remove_names_by_line(
+    names: &[ast::Alias],
+    is_unused: impl Fn(&ast::Alias) -> bool,
+    lines: &[&str],
+    first: usize,
+    last: usize,
+) -> Option<String> {
+    let mut text = String::new();
+    for (row, &line) in lines.iter().enumerate().take(last + 1).skip(first) {
+        let on_line: Vec<&ast::Alias> = names
+            .iter()
+            .filter(|alias| alias.location.row() - 1 == row)
+            .collect();
+        if !on_line.iter().any(|alias| is_unused(alias)) {
+            text.push_str(line);
+            continue;
+        }
+        if row == first || row == last {
+            return None;
+        }
+        let kept: Vec<String> = on_line
+            .into_iter()
+            .filter(|alias| !is_unused(alias))
+            .map(alias_source)
+            .collect();
+        if kept.is_empty() {
+            continue;
+        }
+        let content = line.trim_end();
+        let indent = &content[..content.len() - content.trim_start().len()];
+        let comment = match content.find('#') {
+            Some(start) => format!("  {}", &content[start..]),
+            None => String::new(),
+        };
+        let newline = &line[content.len()..];
+        text.push_str(&format!(
+            "{}{},{}{}",
+            indent,
+            kept.join(", "),
+            comment,
+            newline
+        ));
+    }
+    Some(text)
+}
+
+/// Write each unused import with where it is
+pub fn write_unused_imports(out: &mut impl Write, unused: &[UnusedImport]) -> io::Result<()> {
+    let mut table = Table::new(&["location", "import"]);
+    for import in unused {
+        let name = match &import.alias {
+            Some(alias) => format!("{} as {}", import.name, alias),
+            None => import.name.to_string(),
+        };
+        table.push(vec![
+            format!(
+                "{}:{}:{}",
+                import.path.display(),
+                import.location.line,
+                import.location.column
+            ),
+            name,
+        ]);
+    }
+    table.write_text(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{project::parse_module, testing::TempDir};
+
+    // The unused imports in `source`, and the source after removing them
+    fn fix(name: &str, source: &str) -> (Vec<UnusedImport>, String) {
+        let dir = TempDir::new(&format!("unused-{}", name));
+        let path = dir.write("pkg/m.py", source);
+        let module = parse_module("pkg.m", source, &path, &[]).unwrap();
+        let unused = find_unused_imports(&module);
+        remove_unused_imports(&module, &unused).unwrap();
+        (unused, fs::read_to_string(&path).unwrap())
+    }
+
+    fn names(unused: &[UnusedImport]) -> Vec<&str> {
+        unused.iter().map(|import| import.name.as_str()).collect()
+    }
+
+    #[test]
+    fn multi_line_import() {
+        let source = "\
+from typing import (
+    Any,
+    Optional,  # the optional
+    Union,
+)
+
+x: Optional[int] = None
+";
+        let (unused, fixed) = fix("multi-line", source);
+        assert_eq!(names(&unused), ["Any", "Union"]);
+        assert_eq!(
+            fixed,
+            "\
+from typing import (
+    Optional,  # the optional
+)
+
+x: Optional[int] = None
+"
+        );
+    }
+
+    #[test]
+    fn multi_line_import_shared_lines() {
+        let source = "\
+from typing import (  # typing
+    Any, Optional,  # the optional
+    Union, List,
+    Dict,
+)
+
+x: Optional[List[int]] = None
+";
+        let (unused, fixed) = fix("multi-line-shared", source);
+        assert_eq!(names(&unused), ["Any", "Union", "Dict"]);
+        assert_eq!(
+            fixed,
+            "\
+from typing import (  # typing
+    Optional,  # the optional
+    List,
+)
+
+x: Optional[List[int]] = None
+"
+        );
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        let source = "import os\r\nimport json, sys\r\n\r\nprint(sys.argv)\r\n";
+        let (unused, fixed) = fix("crlf", source);
+        assert_eq!(names(&unused), ["os", "json"]);
+        assert_eq!(fixed, "import sys\r\n\r\nprint(sys.argv)\r\n");
+    }
+
+    #[test]
+    fn trailing_comment() {
+        let source = "import os, sys  # keep me\nprint(sys.argv)\n";
+        let (unused, fixed) = fix("comment", source);
+        assert_eq!(names(&unused), ["os"]);
+        assert_eq!(fixed, "import sys  # keep me\nprint(sys.argv)\n");
+    }
+
+    #[test]
+    fn statements_sharing_a_line() {
+        let source = "import os; import sys\nx = 1\n";
+        let (unused, fixed) = fix("semicolon", source);
+        assert_eq!(names(&unused), ["os", "sys"]);
+        assert_eq!(fixed, source);
+    }
+
+    #[test]
+    fn names_in_all_are_exports() {
+        let source = "from .b import exported, hidden\n\n__all__ = [\"exported\"]\n";
+        let (unused, fixed) = fix("all", source);
+        assert_eq!(names(&unused), ["hidden"]);
+        assert_eq!(
+            fixed,
+            "from .b import exported\n\n__all__ = [\"exported\"]\n"
+        );
+    }
+
+    #[test]
+    fn emptied_block_gets_pass() {
+        let source = "\
+try:
+    import ujson as json
+except ImportError:
+    import json
+    import os
+";
+        let (unused, fixed) = fix("block", source);
+        assert_eq!(names(&unused), ["ujson", "json", "os"]);
+        assert_eq!(fixed, "try:\n    pass\nexcept ImportError:\n    pass\n");
+    }
+}
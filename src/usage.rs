@@ -0,0 +1,161 @@
+use std::{
+    collections::BTreeSet,
+    io::{self, Write},
+};
+
+use crate::{
+    graph::{DependencyGraph, Edge, EdgeKind, NodeKind},
+    table::Table,
+};
+
+// One section of the usage report: a kind of node, the edges to it that
+// count, and the words for them
+struct Section {
+    title: &'static str,
+    kinds: &'static [NodeKind],
+    edges: &'static [EdgeKind],
+    /// List the modules the edges come from, rather than the nodes
+    by_module: bool,
+    count: &'static str,
+    used_by: &'static str,
+}
+
+const REFERENCES: &[EdgeKind] = &[
+    EdgeKind::Call,
+    EdgeKind::Instantiate,
+    EdgeKind::Inherit,
+    EdgeKind::Decorate,
+    EdgeKind::Annotate,
+];
+
+const SECTIONS: &[Section] = &[
+    Section {
+        title: "module",
+        kinds: &[NodeKind::Module],
+        edges: &[EdgeKind::Import],
+        by_module: true,
+        count: "imports",
+        used_by: "imported by",
+    },
+    Section {
+        title: "function",
+        kinds: &[NodeKind::Function, NodeKind::Method],
+        edges: REFERENCES,
+        by_module: false,
+        count: "references",
+        used_by: "referenced by",
+    },
+    Section {
+        title: "class",
+        kinds: &[NodeKind::Class],
+        edges: REFERENCES,
+        by_module: false,
+        count: "references",
+        used_by: "referenced by",
+    },
+];
+
+/// How often something is depended on, and by what
+struct Usage<'a> {
+    name: &'a str,
+    count: usize,
+    used_by: BTreeSet<&'a str>,
+}
+
+// Every module, function, and class in the project, and every module imported
+// from outside it, most used first
+fn usages<'a>(graph: &'a DependencyGraph, section: &Section) -> Vec<Usage<'a>> {
+    let mut usages: Vec<Usage> = graph
+        .nodes()
+        .iter()
+        .enumerate()
+        .filter(|(_, node)| section.kinds.contains(&node.kind))
+        .map(|(id, node)| {
+            let edges: Vec<&Edge> = graph
+                .dependents(id)
+                .filter(|edge| section.edges.contains(&edge.kind))
+                .collect();
+            let used_by = edges.iter().map(|edge| {
+                let from = graph.node(edge.from);
+                if section.by_module {
+                    from.module.as_str()
+                } else {
+                    from.fqn.as_str()
+                }
+            });
+            Usage {
+                name: &node.fqn,
+                count: edges.len(),
+                used_by: used_by.collect(),
+            }
+        })
+        .collect();
+    usages.sort_by(|a, b| b.count.cmp(&a.count).then(a.name.cmp(b.name)));
+    usages
+}
+
+/// Write what each module, function, and class is used by, as aligned
+/// columns with one user per line, or as CSV with one row per symbol
+pub fn write_usage_table(
+    out: &mut impl Write,
+    graph: &DependencyGraph,
+    csv: bool,
+) -> io::Result<()> {
+    if csv {
+        let mut table = Table::new(&["kind", "name", "count", "used_by"]);
+        for section in SECTIONS {
+            for usage in usages(graph, section) {
+                table.push(vec![
+                    section.title.to_string(),
+                    usage.name.to_string(),
+                    usage.count.to_string(),
+                    usage.used_by.into_iter().collect::<Vec<_>>().join(";"),
+                ]);
+            }
+        }
+        return table.write_csv(out);
+    }
+
+    for (index, section) in SECTIONS.iter().enumerate() {
+        if index > 0 {
+            writeln!(out)?;
+        }
+        let mut table = Table::new(&[section.title, section.count, section.used_by]).align_right(1);
+        for usage in usages(graph, section) {
+            let mut used_by = usage.used_by.into_iter();
+            table.push(vec![
+                usage.name.to_string(),
+                usage.count.to_string(),
+                used_by.next().unwrap_or_default().to_string(),
+            ]);
+            for user in used_by {
+                table.push(vec![String::new(), String::new(), user.to_string()]);
+            }
+        }
+        table.write_text(out)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::build_graph;
+
+    #[test]
+    fn imports_and_references_are_counted_apart() {
+        let graph = build_graph(&[
+            ("a.py", "class Base:\n    pass\n\ndef helper():\n    pass\n"),
+            (
+                "b.py",
+                "from a import Base, helper\n\nclass Child(Base):\n    pass\n\ndef run(x: Base):\n    import a\n    helper()\n",
+            ),
+        ]);
+        let mut out = Vec::new();
+        write_usage_table(&mut out, &graph, true).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("module,a,2,b\n"), "{out}");
+        assert!(out.contains("class,a::Base,2,b::Child;b::run\n"), "{out}");
+        assert!(out.contains("function,a::helper,1,b::run\n"), "{out}");
+    }
+}